@@ -0,0 +1,626 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{LingConfig, LingError, LingResult, Value, utils::values_equal_within};
+
+/// Signature shared by all native builtin functions
+pub type BuiltinFn = fn(&[Value]) -> LingResult<Value>;
+
+/// Look up a builtin function by its (Chinese) name
+pub fn lookup(name: &str) -> Option<BuiltinFn> {
+    let builtin: BuiltinFn = match name {
+        "长度" => length,
+        "字符长度" => char_length,
+        "字节长度" => byte_length,
+        "位数" => bit_count,
+        "打包位" => pack_bits,
+        "解包位" => unpack_bits,
+        "是偶数" => is_even,
+        "是奇数" => is_odd,
+        "字符" => to_char,
+        "码点" => code_point,
+        "求和" => sum,
+        "求积" => product,
+        "打包" => zip,
+        "数组反转" => reverse_array,
+        "旋转" => rotate,
+        "分块" => chunks,
+        "窗口" => windows,
+        "二分查找" => binary_search,
+        "有序插入" => sorted_insert,
+        "建映射" => map_from_pairs,
+        "合并" => merge,
+        "删除键" => remove_key,
+        "查找" => find,
+        "出现次数" => occurrences,
+        "替换" => replace,
+        "反转" => reverse,
+        "是回文" => is_palindrome,
+        "左填充" => pad_left,
+        "右填充" => pad_right,
+        "部分应用" => partial,
+        "美化JSON" => pretty_json,
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+/// Callback through which higher-order builtins apply a function value
+pub type Apply<'a> = dyn FnMut(&Value, Vec<Value>) -> LingResult<Value> + 'a;
+
+/// Signature of builtins that take function arguments
+pub type HigherOrderFn = fn(&[Value], &mut Apply) -> LingResult<Value>;
+
+/// Look up a builtin that calls back into user functions
+pub fn lookup_higher_order(name: &str) -> Option<HigherOrderFn> {
+    let builtin: HigherOrderFn = match name {
+        "映射" => map,
+        "筛选" => filter,
+        "折叠" => fold,
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+/// Signature of builtins that depend on the runtime configuration
+pub type ConfiguredFn = fn(&[Value], &LingConfig) -> LingResult<Value>;
+
+/// Look up a builtin whose behaviour the host can pin via `LingConfig`
+pub fn lookup_configured(name: &str) -> Option<ConfiguredFn> {
+    let builtin: ConfiguredFn = match name {
+        "时间" => clock,
+        "去重" => dedup,
+        "计数" => count,
+        _ => return None,
+    };
+    Some(builtin)
+}
+
+/// Check if a name refers to a builtin function
+pub fn is_builtin(name: &str) -> bool {
+    lookup(name).is_some()
+        || lookup_higher_order(name).is_some()
+        || lookup_configured(name).is_some()
+}
+
+/// Ensure a builtin was called with exactly `expected` arguments
+fn expect_args(args: &[Value], expected: usize) -> LingResult<()> {
+    if args.len() != expected {
+        return Err(LingError::ArgumentMismatch {
+            expected,
+            found: args.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Build a type error for an argument of the wrong type
+fn type_error(expected: &str, found: &Value) -> LingError {
+    LingError::TypeError {
+        expected: expected.to_string(),
+        found: found.type_name().to_string(),
+    }
+}
+
+/// Extract a string argument
+fn expect_string(value: &Value) -> LingResult<&str> {
+    match value {
+        Value::String(s) => Ok(s),
+        other => Err(type_error("string", other)),
+    }
+}
+
+/// Extract a number argument
+fn expect_number(value: &Value) -> LingResult<i64> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        other => Err(type_error("number", other)),
+    }
+}
+
+/// Reject an empty search pattern, which would match everywhere
+fn expect_pattern<'a>(op: &str, value: &'a Value) -> LingResult<&'a str> {
+    let pattern = expect_string(value)?;
+    if pattern.is_empty() {
+        return Err(LingError::InvalidOperation {
+            op: op.to_string(),
+            left: "string".to_string(),
+            right: "empty string".to_string(),
+        });
+    }
+    Ok(pattern)
+}
+
+/// Extract a positive size argument for splitting an array
+fn expect_size(op: &str, value: &Value) -> LingResult<usize> {
+    let n = expect_number(value)?;
+    if n <= 0 {
+        return Err(LingError::InvalidOperation {
+            op: op.to_string(),
+            left: "array".to_string(),
+            right: format!("size {}", n),
+        });
+    }
+    Ok(n as usize)
+}
+
+/// Most elements a range argument is expanded into
+const MAX_RANGE_ELEMENTS: usize = 1 << 20;
+
+/// Extract an array argument, expanding a range of up to `MAX_RANGE_ELEMENTS`
+/// into its elements
+fn expect_array(value: &Value) -> LingResult<Cow<'_, [Value]>> {
+    match value {
+        Value::Array(items) => Ok(Cow::Borrowed(items)),
+        Value::Range { .. } => match value.element_count() {
+            Some(count) if count <= MAX_RANGE_ELEMENTS => {
+                Ok(Cow::Owned(value.elements().unwrap().collect()))
+            }
+            _ => Err(LingError::RuntimeError(format!(
+                "range too large: more than {} elements",
+                MAX_RANGE_ELEMENTS
+            ))),
+        },
+        other => Err(type_error("array", other)),
+    }
+}
+
+/// Extract a map argument
+fn expect_map(value: &Value) -> LingResult<&BTreeMap<String, Value>> {
+    match value {
+        Value::Map(entries) => Ok(entries),
+        other => Err(type_error("map", other)),
+    }
+}
+
+/// Fold a numeric array with checked arithmetic, failing on overflow
+fn reduce_numbers(
+    args: &[Value],
+    name: &str,
+    init: i64,
+    op: fn(i64, i64) -> Option<i64>,
+) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let mut acc = init;
+    for item in expect_array(&args[0])?.iter() {
+        let Value::Number(n) = item else {
+            return Err(type_error("number", item));
+        };
+        acc = op(acc, *n)
+            .ok_or_else(|| LingError::RuntimeError(format!("Integer overflow in {}", name)))?;
+    }
+    Ok(Value::Number(acc))
+}
+
+/// 长度(x) - length of a string in chars, or of an array in elements
+fn length(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    match &args[0] {
+        Value::String(s) => Ok(Value::Number(s.chars().count() as i64)),
+        Value::Array(items) => Ok(Value::Number(items.len() as i64)),
+        other => Err(type_error("string or array", other)),
+    }
+}
+
+/// 字符长度(s) - number of chars (你好 → 2)
+fn char_length(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let s = expect_string(&args[0])?;
+    Ok(Value::Number(s.chars().count() as i64))
+}
+
+/// 字节长度(s) - number of UTF-8 bytes (你好 → 6)
+fn byte_length(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let s = expect_string(&args[0])?;
+    Ok(Value::Number(s.len() as i64))
+}
+
+/// 位数(n) - number of set bits (7 → 3)
+fn bit_count(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let n = expect_number(&args[0])?;
+    Ok(Value::Number(n.count_ones() as i64))
+}
+
+/// 打包位(a) - pack up to 64 booleans into a bitmask, the first element as
+/// the least significant bit (⟬真, 假, 真⟭ → 5)
+fn pack_bits(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let items = expect_array(&args[0])?;
+    if items.len() > 64 {
+        return Err(LingError::InvalidOperation {
+            op: "打包位".to_string(),
+            left: "array".to_string(),
+            right: format!("{} bits", items.len()),
+        });
+    }
+    let mut mask = 0u64;
+    for (bit, item) in items.iter().enumerate() {
+        match item {
+            Value::Boolean(true) => mask |= 1 << bit,
+            Value::Boolean(false) => {}
+            other => return Err(type_error("boolean", other)),
+        }
+    }
+    Ok(Value::Number(mask as i64))
+}
+
+/// 解包位(n, width) - the low `width` bits of n as booleans, least
+/// significant first
+fn unpack_bits(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let mask = expect_number(&args[0])? as u64;
+    let width = expect_number(&args[1])?;
+    if !(0..=64).contains(&width) {
+        return Err(LingError::InvalidOperation {
+            op: "解包位".to_string(),
+            left: "number".to_string(),
+            right: format!("width {}", width),
+        });
+    }
+    let bits = (0..width)
+        .map(|bit| Value::Boolean(mask >> bit & 1 == 1))
+        .collect();
+    Ok(Value::Array(bits))
+}
+
+/// 是偶数(n) - whether n is even
+fn is_even(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    Ok(Value::Boolean(expect_number(&args[0])? % 2 == 0))
+}
+
+/// 是奇数(n) - whether n is odd
+fn is_odd(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    Ok(Value::Boolean(expect_number(&args[0])? % 2 != 0))
+}
+
+/// 字符(n) - the char with code point n (97 → a)
+fn to_char(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let n = expect_number(&args[0])?;
+    u32::try_from(n)
+        .ok()
+        .and_then(char::from_u32)
+        .map(Value::Char)
+        .ok_or_else(|| LingError::RuntimeError(format!("Invalid code point: {}", n)))
+}
+
+/// 码点(c) - the code point of a char or one-char string (你 → 20320)
+fn code_point(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    match &args[0] {
+        Value::Char(c) => Ok(Value::Number(*c as i64)),
+        Value::String(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(Value::Number(c as i64)),
+                _ => Err(LingError::TypeError {
+                    expected: "char".to_string(),
+                    found: format!("string of {} chars", s.chars().count()),
+                }),
+            }
+        }
+        other => Err(type_error("char", other)),
+    }
+}
+
+/// 求和(a) - sum of a numeric array (empty → 0)
+fn sum(args: &[Value]) -> LingResult<Value> {
+    reduce_numbers(args, "求和", 0, i64::checked_add)
+}
+
+/// 求积(a) - product of a numeric array (empty → 1)
+fn product(args: &[Value]) -> LingResult<Value> {
+    reduce_numbers(args, "求积", 1, i64::checked_mul)
+}
+
+/// 打包(a, b) - pair up elements of two arrays, truncating to the shorter
+fn zip(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let left = expect_array(&args[0])?;
+    let right = expect_array(&args[1])?;
+    let pairs = left
+        .iter()
+        .zip(right.iter())
+        .map(|(a, b)| Value::Array(vec![a.clone(), b.clone()]))
+        .collect::<Vec<_>>();
+    Ok(Value::Array(pairs))
+}
+
+/// 去重(a) - new array keeping only the first occurrence of each value,
+/// treating floats within `float_epsilon` as the same value
+fn dedup(args: &[Value], config: &LingConfig) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let mut unique: Vec<Value> = Vec::new();
+    for item in expect_array(&args[0])?.iter() {
+        if !unique
+            .iter()
+            .any(|seen| values_equal_within(seen, item, config.float_epsilon))
+        {
+            unique.push(item.clone());
+        }
+    }
+    Ok(Value::Array(unique))
+}
+
+/// 计数(a, x) - number of elements equal to x, within `float_epsilon` for floats
+fn count(args: &[Value], config: &LingConfig) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let items = expect_array(&args[0])?;
+    let matches = items
+        .iter()
+        .filter(|item| values_equal_within(item, &args[1], config.float_epsilon))
+        .count();
+    Ok(Value::Number(matches as i64))
+}
+
+/// 数组反转(a) - new array with the elements in reverse order
+fn reverse_array(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let mut items = expect_array(&args[0])?.into_owned();
+    items.reverse();
+    Ok(Value::Array(items))
+}
+
+/// 旋转(a, n) - new array rotated left by n positions (right when n < 0)
+fn rotate(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let mut items = expect_array(&args[0])?.into_owned();
+    let n = expect_number(&args[1])?;
+    if !items.is_empty() {
+        let shift = n.rem_euclid(items.len() as i64) as usize;
+        items.rotate_left(shift);
+    }
+    Ok(Value::Array(items))
+}
+
+/// 分块(a, n) - consecutive n-element sub-arrays; the last may be shorter
+fn chunks(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let items = expect_array(&args[0])?;
+    let size = expect_size("分块", &args[1])?;
+    let chunks = items
+        .chunks(size)
+        .map(|c| Value::Array(c.to_vec()))
+        .collect();
+    Ok(Value::Array(chunks))
+}
+
+/// 窗口(a, n) - every contiguous n-element window, none when n exceeds the length
+fn windows(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let items = expect_array(&args[0])?;
+    let size = expect_size("窗口", &args[1])?;
+    let windows = items
+        .windows(size)
+        .map(|w| Value::Array(w.to_vec()))
+        .collect();
+    Ok(Value::Array(windows))
+}
+
+/// Where `x` belongs in the sorted `items`: `Ok` with its index when present,
+/// otherwise `Err` with the insertion point
+fn search_sorted(items: &[Value], x: &Value) -> LingResult<Result<usize, usize>> {
+    let mut failed = None;
+    let position = items.binary_search_by(|item| match item.cmp_value(x) {
+        Ok(order) => order.cmp(&0),
+        Err(error) => {
+            failed.get_or_insert(error);
+            Ordering::Equal
+        }
+    });
+    match failed {
+        Some(error) => Err(error),
+        None => Ok(position),
+    }
+}
+
+/// 二分查找(a, x) - index of x in the sorted array a, or -1; the result is
+/// unspecified when a is not sorted
+fn binary_search(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let items = expect_array(&args[0])?;
+    let index = match search_sorted(&items, &args[1])? {
+        Ok(index) => index as i64,
+        Err(_) => -1,
+    };
+    Ok(Value::Number(index))
+}
+
+/// 有序插入(a, x) - new sorted array with x inserted into the sorted array a
+fn sorted_insert(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let mut items = expect_array(&args[0])?.into_owned();
+    let (Ok(index) | Err(index)) = search_sorted(&items, &args[1])?;
+    items.insert(index, args[1].clone());
+    Ok(Value::Array(items))
+}
+
+/// 建映射(pairs) - map from an array of [key, value] pairs; later keys win
+fn map_from_pairs(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let mut entries = BTreeMap::new();
+    for item in expect_array(&args[0])?.iter() {
+        match item {
+            Value::Array(pair) if pair.len() == 2 => match &pair[0] {
+                Value::String(key) => {
+                    entries.insert(key.clone(), pair[1].clone());
+                }
+                other => return Err(type_error("string", other)),
+            },
+            other => return Err(type_error("[key, value] pair", other)),
+        }
+    }
+    Ok(Value::Map(entries))
+}
+
+/// 合并(a, b) - new map with b's entries overriding a's
+fn merge(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let mut merged = expect_map(&args[0])?.clone();
+    merged.extend(expect_map(&args[1])?.clone());
+    Ok(Value::Map(merged))
+}
+
+/// 删除键(m, key) - new map without `key` (absent keys are a no-op)
+fn remove_key(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let mut entries = expect_map(&args[0])?.clone();
+    entries.remove(expect_string(&args[1])?);
+    Ok(Value::Map(entries))
+}
+
+/// 映射(a, f) - new array with f applied to each element
+fn map(args: &[Value], apply: &mut Apply) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let items = expect_array(&args[0])?;
+    let mapped = items
+        .iter()
+        .map(|item| apply(&args[1], vec![item.clone()]))
+        .collect::<LingResult<Vec<_>>>()?;
+    Ok(Value::Array(mapped))
+}
+
+/// 筛选(a, f) - new array of the elements for which f is truthy
+fn filter(args: &[Value], apply: &mut Apply) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let mut kept = Vec::new();
+    for item in expect_array(&args[0])?.iter() {
+        if apply(&args[1], vec![item.clone()])?.is_truthy() {
+            kept.push(item.clone());
+        }
+    }
+    Ok(Value::Array(kept))
+}
+
+/// 折叠(a, init, f) - f(acc, x) applied left to right, starting from init
+fn fold(args: &[Value], apply: &mut Apply) -> LingResult<Value> {
+    expect_args(args, 3)?;
+    let mut acc = args[1].clone();
+    for item in expect_array(&args[0])?.iter() {
+        acc = apply(&args[2], vec![acc, item.clone()])?;
+    }
+    Ok(acc)
+}
+
+/// 部分应用(f, args) - f with args bound as its leading arguments
+fn partial(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let Value::Function(func) = &args[0] else {
+        return Err(type_error("function", &args[0]));
+    };
+    Ok(Value::Function(func.bind(&expect_array(&args[1])?)?))
+}
+
+/// 美化JSON(v) - v as JSON indented by two spaces per level
+fn pretty_json(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    Ok(Value::String(args[0].to_json_pretty(2)))
+}
+
+/// 查找(s, needle) - char index of the first match, or -1
+fn find(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let haystack = expect_string(&args[0])?;
+    let needle = expect_pattern("查找", &args[1])?;
+    let index = haystack
+        .find(needle)
+        .map_or(-1, |byte| haystack[..byte].chars().count() as i64);
+    Ok(Value::Number(index))
+}
+
+/// 出现次数(s, needle) - how many times needle appears, not counting overlaps
+fn occurrences(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 2)?;
+    let haystack = expect_string(&args[0])?;
+    let needle = expect_pattern("出现次数", &args[1])?;
+    Ok(Value::Number(haystack.matches(needle).count() as i64))
+}
+
+/// 替换(s, from, to) - new string with every `from` replaced by `to`
+fn replace(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 3)?;
+    let s = expect_string(&args[0])?;
+    let from = expect_pattern("替换", &args[1])?;
+    let to = expect_string(&args[2])?;
+    Ok(Value::String(s.replace(from, to)))
+}
+
+/// 反转(s) - the string with its chars in reverse order (你好 → 好你)
+fn reverse(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let s = expect_string(&args[0])?;
+    Ok(Value::String(s.chars().rev().collect()))
+}
+
+/// 是回文(s) - whether the string reads the same reversed, char by char
+fn is_palindrome(args: &[Value]) -> LingResult<Value> {
+    expect_args(args, 1)?;
+    let s = expect_string(&args[0])?;
+    Ok(Value::Boolean(s.chars().eq(s.chars().rev())))
+}
+
+/// Widest a padded string may be, in terminal cells
+const MAX_PAD_WIDTH: i64 = 1 << 20;
+
+/// 左填充(s, width, pad) - pad on the left to `width` cells
+fn pad_left(args: &[Value]) -> LingResult<Value> {
+    pad("左填充", args, true)
+}
+
+/// 右填充(s, width, pad) - pad on the right to `width` cells
+fn pad_right(args: &[Value]) -> LingResult<Value> {
+    pad("右填充", args, false)
+}
+
+/// Pad a string with as many copies of a single pad char as fit in a width
+/// counted in terminal cells (see `Value::display_width`), so CJK text lines
+/// up; strings already that wide are returned unchanged
+fn pad(op: &str, args: &[Value], left: bool) -> LingResult<Value> {
+    expect_args(args, 3)?;
+    let s = expect_string(&args[0])?;
+    let width = expect_number(&args[1])?;
+    let pad = expect_string(&args[2])?;
+    let mut chars = pad.chars();
+    let (Some(pad_char), None) = (chars.next(), chars.next()) else {
+        return Err(LingError::InvalidOperation {
+            op: op.to_string(),
+            left: "string".to_string(),
+            right: format!("pad of {} chars", pad.chars().count()),
+        });
+    };
+
+    if width > MAX_PAD_WIDTH {
+        return Err(LingError::RuntimeError(format!(
+            "pad width too large: more than {} cells",
+            MAX_PAD_WIDTH
+        )));
+    }
+
+    let missing = (width.max(0) as usize).saturating_sub(args[0].display_width());
+    let pad_width = Value::Char(pad_char).display_width().max(1);
+    let fill = pad_char.to_string().repeat(missing / pad_width);
+    Ok(Value::String(if left {
+        fill + s
+    } else {
+        s.to_string() + &fill
+    }))
+}
+
+/// 时间() - milliseconds since the Unix epoch, or `fixed_clock_ms` when set
+fn clock(args: &[Value], config: &LingConfig) -> LingResult<Value> {
+    expect_args(args, 0)?;
+    if let Some(ms) = config.fixed_clock_ms {
+        return Ok(Value::Number(ms));
+    }
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| LingError::RuntimeError(e.to_string()))?;
+    Ok(Value::Number(elapsed.as_millis() as i64))
+}
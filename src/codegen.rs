@@ -1,12 +1,22 @@
 use inkwell::AddressSpace;
 use inkwell::IntPredicate;
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
-use inkwell::module::Module;
+use inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFlags, DIFlagsConstants, DIScope, DWARFEmissionKind,
+    DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::{FlagBehavior, Module};
 use inkwell::types::BasicType;
 use inkwell::types::{BasicMetadataTypeEnum, BasicTypeEnum};
 use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
 use std::collections::HashMap;
+use std::num::IntErrorKind;
+use std::path::Path;
+
+use crate::ling_number::chinese_to_number;
+use crate::parser::{Expression, Statement};
 
 /// Represents parts of a string expression (for concatenation)
 #[derive(Debug, Clone)]
@@ -16,21 +26,37 @@ pub enum StringPart {
 }
 
 /// Runtime value wrapper for LLVM values
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(dead_code)]
 pub enum RuntimeValue<'ctx> {
     String(PointerValue<'ctx>),
     Integer(IntValue<'ctx>),
 }
 
-/// Symbol table entry for variables
+/// Symbol table entry for variables: the entry-block slot holding the value,
+/// so every block that assigns or reads the variable goes through memory
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 struct Symbol<'ctx> {
-    value: RuntimeValue<'ctx>,
+    slot: PointerValue<'ctx>,
+    is_string: bool,
     is_mutable: bool,
 }
 
+/// Blocks of an if/else being emitted: see `build_if`, `build_else`, `end_if`
+pub struct IfBlocks<'ctx> {
+    else_block: BasicBlock<'ctx>,
+    merge_block: BasicBlock<'ctx>,
+}
+
+/// DWARF debug info state, created when the first function is emitted
+struct DebugInfo<'ctx> {
+    builder: DebugInfoBuilder<'ctx>,
+    compile_unit: DICompileUnit<'ctx>,
+    // Scope of the function currently being compiled
+    scope: Option<DIScope<'ctx>>,
+}
+
 pub struct Compiler<'ctx> {
     context: &'ctx Context,
     module: Module<'ctx>,
@@ -45,8 +71,18 @@ pub struct Compiler<'ctx> {
     // Counter for generating unique names
     temp_counter: usize,
 
+    // Interned string literals, so repeated text shares one global
+    string_literals: HashMap<String, PointerValue<'ctx>>,
+
+    // Concatenation buffer owned by each variable, reused on reassignment
+    concat_buffers: HashMap<String, PointerValue<'ctx>>,
+
     // Current function being compiled
     current_function: Option<FunctionValue<'ctx>>,
+
+    // Whether to emit DWARF line tables mapping instructions to source lines
+    emit_debug_info: bool,
+    debug_info: Option<DebugInfo<'ctx>>,
 }
 
 #[allow(dead_code)]
@@ -63,7 +99,11 @@ impl<'ctx> Compiler<'ctx> {
             symbols: HashMap::new(),
             alien_symbol_map: HashMap::new(),
             temp_counter: 0,
+            string_literals: HashMap::new(),
+            concat_buffers: HashMap::new(),
             current_function: None,
+            emit_debug_info: false,
+            debug_info: None,
         };
 
         compiler.init_alien_symbols();
@@ -71,6 +111,17 @@ impl<'ctx> Compiler<'ctx> {
         compiler
     }
 
+    /// Enable or disable DWARF debug info (source line mapping for gdb/lldb)
+    pub fn with_debug_info(mut self, enabled: bool) -> Self {
+        self.emit_debug_info = enabled;
+        self
+    }
+
+    /// Set the source file path recorded in the module and its debug info
+    pub fn set_source_file(&self, path: &str) {
+        self.module.set_source_file_name(path);
+    }
+
     /// Declare all standard library functions at once
     pub fn declare_stdlib(&self) {
         self.declare_printf();
@@ -187,6 +238,9 @@ impl<'ctx> Compiler<'ctx> {
         let entry = self.context.append_basic_block(main_fn, "entry");
         self.builder.position_at_end(entry);
         self.current_function = Some(main_fn);
+        self.symbols.clear();
+        self.concat_buffers.clear();
+        self.attach_debug_subprogram(main_fn, "main");
         main_fn
     }
 
@@ -207,9 +261,113 @@ impl<'ctx> Compiler<'ctx> {
         let entry = self.context.append_basic_block(function, "entry");
         self.builder.position_at_end(entry);
         self.current_function = Some(function);
+        self.symbols.clear();
+        self.concat_buffers.clear();
+        self.attach_debug_subprogram(function, name);
         function
     }
 
+    /// Create the debug info builder and compile unit on first use
+    fn ensure_debug_info(&mut self) -> Option<&mut DebugInfo<'ctx>> {
+        if !self.emit_debug_info {
+            return None;
+        }
+
+        if self.debug_info.is_none() {
+            let source = self
+                .module
+                .get_source_file_name()
+                .to_string_lossy()
+                .to_string();
+            let path = Path::new(&source);
+            let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or(&source);
+            let directory = path.parent().and_then(|d| d.to_str()).unwrap_or(".");
+
+            let debug_metadata_version = self.context.i32_type().const_int(3, false);
+            self.module.add_basic_value_flag(
+                "Debug Info Version",
+                FlagBehavior::Warning,
+                debug_metadata_version,
+            );
+
+            let (builder, compile_unit) = self.module.create_debug_info_builder(
+                true,
+                DWARFSourceLanguage::C,
+                file_name,
+                directory,
+                "ling-lang",
+                false,
+                "",
+                0,
+                "",
+                DWARFEmissionKind::Full,
+                0,
+                false,
+                false,
+                "",
+                "",
+            );
+
+            self.debug_info = Some(DebugInfo {
+                builder,
+                compile_unit,
+                scope: None,
+            });
+        }
+
+        self.debug_info.as_mut()
+    }
+
+    /// Attach a debug subprogram to a function so its instructions get a scope
+    fn attach_debug_subprogram(&mut self, function: FunctionValue<'ctx>, name: &str) {
+        let Some(debug_info) = self.ensure_debug_info() else {
+            return;
+        };
+
+        let file = debug_info.compile_unit.get_file();
+        let subroutine_type =
+            debug_info
+                .builder
+                .create_subroutine_type(file, None, &[], DIFlags::PUBLIC);
+        let subprogram = debug_info.builder.create_function(
+            debug_info.compile_unit.as_debug_info_scope(),
+            name,
+            None,
+            file,
+            1,
+            subroutine_type,
+            true,
+            true,
+            1,
+            DIFlags::PUBLIC,
+            false,
+        );
+
+        function.set_subprogram(subprogram);
+        debug_info.scope = Some(subprogram.as_debug_info_scope());
+    }
+
+    /// Map subsequently emitted instructions to a source line/column
+    /// (no-op unless debug info is enabled)
+    pub fn set_source_location(&self, line: u32, column: u32) {
+        if let Some(debug_info) = &self.debug_info
+            && let Some(scope) = debug_info.scope
+        {
+            let location =
+                debug_info
+                    .builder
+                    .create_debug_location(self.context, line, column, scope, None);
+            self.builder.set_current_debug_location(location);
+        }
+    }
+
+    /// Finalize pending debug info before the module is emitted
+    fn finalize_debug_info(&self) {
+        if let Some(debug_info) = &self.debug_info {
+            debug_info.builder.finalize();
+        }
+    }
+
     /// Finish the current function with return
     pub fn finish_function(&self, return_value: Option<BasicValueEnum<'ctx>>) {
         if let Some(val) = return_value {
@@ -221,25 +379,50 @@ impl<'ctx> Compiler<'ctx> {
 
     /// Finish main function with return 0
     pub fn finish_main(&self) {
+        self.finish_main_with_code(0);
+    }
+
+    /// Finish main function, returning `code` as the process exit status
+    pub fn finish_main_with_code(&self, code: i64) {
+        let i32_type = self.context.i32_type();
+        let status = i32_type.const_int(code as u64, true);
+        self.builder.build_return(Some(&status)).unwrap();
+    }
+
+    /// Finish main function with a status computed at runtime
+    pub fn finish_main_with_status(&self, code: IntValue<'ctx>) {
         let i32_type = self.context.i32_type();
-        let zero = i32_type.const_int(0, false);
-        self.builder.build_return(Some(&zero)).unwrap();
+        let status = self
+            .builder
+            .build_int_cast(code, i32_type, "status")
+            .unwrap();
+        self.builder.build_return(Some(&status)).unwrap();
     }
 
-    /// Parse alien/Chinese numbers to i64
-    pub fn parse_number(&self, num_str: &str) -> i64 {
+    /// Parse alien/Chinese numbers to i64, failing when the value overflows
+    pub fn parse_number(&self, num_str: &str) -> Result<i64, String> {
+        let too_large = || "number too large".to_string();
+
         // Try ASCII number first
-        if let Ok(n) = num_str.parse::<i64>() {
-            return n;
+        match num_str.parse::<i64>() {
+            Ok(n) => return Ok(n),
+            Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow) => return Err(too_large()),
+            Err(_) => {}
         }
 
-        // Check if it's a repeated operator number (⊕⊕⊕⊕⊕ = 5)
+        // Check if it's a repeated operator number (⊕⊕⊕⊕⊕ = 5); alien digits
+        // are left to the positional reading below (∀∀ = 33)
         if let Some(first_char) = num_str.chars().next() {
-            if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸∀∃∄∅".contains(first_char) {
+            if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸".contains(first_char) {
                 let count = num_str.chars().take_while(|&c| c == first_char).count();
 
                 if count == num_str.chars().count() {
-                    return count as i64;
+                    // A single symbol uses the literal table instead (⊙ = 5)
+                    return Ok(if count == 1 {
+                        self.parse_operator_literal(num_str)
+                    } else {
+                        count as i64
+                    });
                 }
             }
         }
@@ -252,97 +435,11 @@ impl<'ctx> Compiler<'ctx> {
                     result.push_str(digit);
                 }
             }
-            return result.parse().unwrap_or(0);
+            return result.parse().map_err(|_| too_large());
         }
 
-        // Handle Chinese numbers
-        self.parse_chinese_number(num_str)
-    }
-
-    /// Parse Chinese numerals
-    fn parse_chinese_number(&self, s: &str) -> i64 {
-        let mut result = 0i64;
-        let mut current = 0i64;
-        let mut has_digit = false;
-
-        for ch in s.chars() {
-            match ch {
-                '零' | '〇' => {
-                    has_digit = true;
-                    current = 0;
-                }
-                '一' => {
-                    has_digit = true;
-                    current = 1;
-                }
-                '二' => {
-                    has_digit = true;
-                    current = 2;
-                }
-                '三' => {
-                    has_digit = true;
-                    current = 3;
-                }
-                '四' => {
-                    has_digit = true;
-                    current = 4;
-                }
-                '五' => {
-                    has_digit = true;
-                    current = 5;
-                }
-                '六' => {
-                    has_digit = true;
-                    current = 6;
-                }
-                '七' => {
-                    has_digit = true;
-                    current = 7;
-                }
-                '八' => {
-                    has_digit = true;
-                    current = 8;
-                }
-                '九' => {
-                    has_digit = true;
-                    current = 9;
-                }
-                '十' => {
-                    if !has_digit {
-                        current = 1;
-                    }
-                    result += current * 10;
-                    current = 0;
-                    has_digit = false;
-                }
-                '百' => {
-                    if !has_digit {
-                        current = 1;
-                    }
-                    result += current * 100;
-                    current = 0;
-                    has_digit = false;
-                }
-                '千' => {
-                    if !has_digit {
-                        current = 1;
-                    }
-                    result += current * 1000;
-                    current = 0;
-                    has_digit = false;
-                }
-                '万' => {
-                    if !has_digit {
-                        current = 1;
-                    }
-                    result = (result + current) * 10000;
-                    current = 0;
-                    has_digit = false;
-                }
-                _ => {}
-            }
-        }
-        result + current
+        // Chinese numerals are read the same way as in the parsed AST
+        chinese_to_number(num_str).ok_or_else(too_large)
     }
 
     /// Parse single operator symbols as numeric values
@@ -356,7 +453,12 @@ impl<'ctx> Compiler<'ctx> {
             "⊞" => 1,
             "⊟" => 0,
             "⊠" => 2,
-            _ => 0,
+            // Single alien digits (∅ = 0 … ℈ = 9), as in parse_number
+            _ => self
+                .alien_symbol_map
+                .get(op)
+                .and_then(|digit| digit.parse().ok())
+                .unwrap_or(0),
         }
     }
 
@@ -382,38 +484,155 @@ impl<'ctx> Compiler<'ctx> {
 
     /// Store an integer variable
     pub fn store_integer(&mut self, var_name: &str, value: i64) {
-        let i64_type = self.context.i64_type();
-        let alloca = self.builder.build_alloca(i64_type, var_name).unwrap();
-        let int_val = i64_type.const_int(value as u64, false);
-        self.builder.build_store(alloca, int_val).unwrap();
-
-        let loaded = self.builder.build_load(i64_type, alloca, "load").unwrap();
-        self.symbols.insert(
-            var_name.to_string(),
-            Symbol {
-                value: RuntimeValue::Integer(loaded.into_int_value()),
-                is_mutable: true,
-            },
-        );
+        let int_val = self.const_integer(value);
+        self.store_value(var_name, RuntimeValue::Integer(int_val));
+    }
+
+    /// Bind a variable to an already computed value, storing it into the
+    /// variable's slot (allocated on first assignment)
+    pub fn store_value(&mut self, var_name: &str, value: RuntimeValue<'ctx>) {
+        let (stored, is_string): (BasicValueEnum<'ctx>, bool) = match value {
+            RuntimeValue::String(ptr) => (ptr.into(), true),
+            RuntimeValue::Integer(int_val) => (int_val.into(), false),
+        };
+        let slot = match self.symbols.get(var_name) {
+            Some(sym) if sym.is_string == is_string => sym.slot,
+            _ => {
+                let slot = self
+                    .entry_builder()
+                    .build_alloca(stored.get_type(), var_name)
+                    .unwrap();
+                self.symbols.insert(
+                    var_name.to_string(),
+                    Symbol {
+                        slot,
+                        is_string,
+                        is_mutable: true,
+                    },
+                );
+                slot
+            }
+        };
+        self.builder.build_store(slot, stored).unwrap();
+    }
+
+    /// Builder positioned at the top of the current function's entry block,
+    /// where allocas dominate every use
+    fn entry_builder(&self) -> Builder<'ctx> {
+        let entry_builder = self.context.create_builder();
+        let entry = self
+            .current_function
+            .and_then(|f| f.get_first_basic_block())
+            .expect("variable outside of a function");
+        match entry.get_first_instruction() {
+            Some(first) => entry_builder.position_before(&first),
+            None => entry_builder.position_at_end(entry),
+        }
+        entry_builder
+    }
+
+    /// Get a pointer to a constant string, reusing the global if the same
+    /// literal was emitted before
+    pub fn intern_string(&mut self, value: &str) -> PointerValue<'ctx> {
+        if let Some(&ptr) = self.string_literals.get(value) {
+            return ptr;
+        }
+
+        let name = self.gen_temp_name("str");
+        let ptr = self
+            .builder
+            .build_global_string_ptr(value, &name)
+            .unwrap()
+            .as_pointer_value();
+        self.string_literals.insert(value.to_string(), ptr);
+        ptr
     }
 
     /// Store a string variable
     pub fn store_string(&mut self, var_name: &str, value: &str) {
-        let name = self.gen_temp_name("str");
-        let global = self.builder.build_global_string_ptr(value, &name).unwrap();
+        let global = self.intern_string(value);
+        self.store_value(var_name, RuntimeValue::String(global));
+    }
 
-        self.symbols.insert(
-            var_name.to_string(),
-            Symbol {
-                value: RuntimeValue::String(global.as_pointer_value()),
-                is_mutable: true,
-            },
-        );
+    /// Build an i64 constant
+    pub fn const_integer(&self, value: i64) -> IntValue<'ctx> {
+        self.context.i64_type().const_int(value as u64, true)
+    }
+
+    /// Print a constant string
+    pub fn print_literal(&mut self, text: &str) {
+        let ptr = self.intern_string(text);
+        self.print_value(RuntimeValue::String(ptr));
+    }
+
+    /// Print an i1 comparison result as 真 or 假
+    pub fn print_bool(&mut self, cond: IntValue<'ctx>) {
+        let yes = self.intern_string("真");
+        let no = self.intern_string("假");
+        let text = self
+            .builder
+            .build_select(cond, yes, no, "bool_str")
+            .unwrap()
+            .into_pointer_value();
+        self.print_value(RuntimeValue::String(text));
     }
 
-    /// Get a variable's runtime value
+    /// Branch on `cond` and position the builder in the then-block
+    pub fn build_if(&mut self, cond: IntValue<'ctx>) -> IfBlocks<'ctx> {
+        let function = self.current_function.expect("if outside of a function");
+        let then_block = self.context.append_basic_block(function, "then");
+        let else_block = self.context.append_basic_block(function, "else");
+        let merge_block = self.context.append_basic_block(function, "endif");
+
+        self.builder
+            .build_conditional_branch(cond, then_block, else_block)
+            .unwrap();
+        self.builder.position_at_end(then_block);
+
+        IfBlocks {
+            else_block,
+            merge_block,
+        }
+    }
+
+    /// Close the then-block and position the builder in the else-block
+    pub fn build_else(&mut self, blocks: &IfBlocks<'ctx>) {
+        self.branch_unless_terminated(blocks.merge_block);
+        self.builder.position_at_end(blocks.else_block);
+    }
+
+    /// Close the else-block and continue after the if/else
+    pub fn end_if(&mut self, blocks: IfBlocks<'ctx>) {
+        self.branch_unless_terminated(blocks.merge_block);
+        self.builder.position_at_end(blocks.merge_block);
+    }
+
+    /// Jump to `target` unless the current block already ended (e.g. by an exit)
+    fn branch_unless_terminated(&self, target: BasicBlock<'ctx>) {
+        let terminated = self
+            .builder
+            .get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_some();
+        if !terminated {
+            self.builder.build_unconditional_branch(target).unwrap();
+        }
+    }
+
+    /// Load a variable's current value from its slot
     pub fn get_variable(&self, var_name: &str) -> Option<RuntimeValue<'ctx>> {
-        self.symbols.get(var_name).map(|sym| sym.value)
+        let sym = self.symbols.get(var_name)?;
+        let value = if sym.is_string {
+            let ptr_type = self.context.ptr_type(AddressSpace::default());
+            let loaded = self.builder.build_load(ptr_type, sym.slot, var_name);
+            RuntimeValue::String(loaded.unwrap().into_pointer_value())
+        } else {
+            let loaded = self
+                .builder
+                .build_load(self.context.i64_type(), sym.slot, var_name);
+            RuntimeValue::Integer(loaded.unwrap().into_int_value())
+        };
+        Some(value)
     }
 
     /// Print a value (string or integer)
@@ -459,59 +678,82 @@ impl<'ctx> Compiler<'ctx> {
         }
     }
 
-    /// Concatenate strings at runtime
+    /// Concatenate strings at runtime into `var_name`
     pub fn concat_strings(&mut self, var_name: &str, parts: Vec<StringPart>) -> PointerValue<'ctx> {
-        let sprintf = self.module.get_function("sprintf").unwrap();
-        let i8_type = self.context.i8_type();
+        let buffer = self.concat_into(var_name, &parts);
+        self.store_value(var_name, RuntimeValue::String(buffer));
+        buffer
+    }
 
-        // Allocate buffer for result
-        let buffer = self
-            .builder
-            .build_array_alloca(i8_type, i8_type.const_int(1024, false), "concat_buffer")
-            .unwrap();
+    /// Concatenate `parts` into the buffer owned by `owner`, without binding it
+    fn concat_into(&mut self, owner: &str, parts: &[StringPart]) -> PointerValue<'ctx> {
+        let sprintf = self.module.get_function("sprintf").unwrap();
 
         // Build format string and collect arguments
         let mut format = String::new();
-        let mut args = vec![buffer.into()];
+        let mut args = Vec::new();
 
-        for part in &parts {
+        for part in parts {
             match part {
                 StringPart::Literal(s) => {
                     format.push_str(s);
                 }
-                StringPart::Variable(v) => {
-                    format.push_str("%s");
-                    if let Some(RuntimeValue::String(ptr)) = self.get_variable(v) {
+                StringPart::Variable(v) => match self.get_variable(v) {
+                    Some(RuntimeValue::String(ptr)) => {
+                        format.push_str("%s");
                         args.push(ptr.into());
                     }
-                }
+                    Some(RuntimeValue::Integer(int_val)) => {
+                        format.push_str("%lld");
+                        args.push(int_val.into());
+                    }
+                    None => {}
+                },
             }
         }
 
-        let fmt_name = self.gen_temp_name("fmt");
-        let format_str = self
-            .builder
-            .build_global_string_ptr(&format, &fmt_name)
-            .unwrap();
-        args.insert(1, format_str.as_pointer_value().into());
+        let buffer = self.concat_buffer(owner, parts);
+        let format_str = self.intern_string(&format);
+        args.insert(0, buffer.into());
+        args.insert(1, format_str.into());
 
         // Call sprintf
         self.builder
             .build_call(sprintf, &args, "sprintf_call")
             .unwrap();
 
-        // Store result
-        self.symbols.insert(
-            var_name.to_string(),
-            Symbol {
-                value: RuntimeValue::String(buffer),
-                is_mutable: true,
-            },
-        );
+        buffer
+    }
+
+    /// Buffer to concatenate into for `var_name`. The variable's previous buffer
+    /// is reused unless the new value reads the variable itself; buffers another
+    /// variable was bound to are dropped by `share_buffer`. Fresh buffers are
+    /// allocated in the entry block
+    fn concat_buffer(&mut self, var_name: &str, parts: &[StringPart]) -> PointerValue<'ctx> {
+        if let Some(&buffer) = self.concat_buffers.get(var_name) {
+            let read = parts
+                .iter()
+                .any(|part| matches!(part, StringPart::Variable(v) if v == var_name));
+            if !read {
+                return buffer;
+            }
+        }
 
+        let i8_type = self.context.i8_type();
+        let buffer = self
+            .entry_builder()
+            .build_array_alloca(i8_type, i8_type.const_int(1024, false), "concat_buffer")
+            .unwrap();
+        self.concat_buffers.insert(var_name.to_string(), buffer);
         buffer
     }
 
+    /// `var_name`'s value may now also be read through another variable, so its
+    /// buffer must not be overwritten by the next concatenation into it
+    fn share_buffer(&mut self, var_name: &str) {
+        self.concat_buffers.remove(var_name);
+    }
+
     /// Build arithmetic operations
     pub fn build_arithmetic(
         &self,
@@ -527,6 +769,10 @@ impl<'ctx> Compiler<'ctx> {
                 .builder
                 .build_int_signed_div(left, right, "div")
                 .unwrap(),
+            "%" | "⊛" => self
+                .builder
+                .build_int_signed_rem(left, right, "rem")
+                .unwrap(),
             _ => panic!("Unknown arithmetic operator: {}", op),
         }
     }
@@ -553,8 +799,180 @@ impl<'ctx> Compiler<'ctx> {
             .unwrap()
     }
 
+    /// Compile parsed statements into the current function; returns true once
+    /// an exit or return has terminated it
+    pub fn compile_program(&mut self, statements: &[Statement]) -> Result<bool, String> {
+        for statement in statements {
+            if self.compile_statement(statement)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Compile one parsed statement; returns true once it has terminated the function
+    pub fn compile_statement(&mut self, statement: &Statement) -> Result<bool, String> {
+        match statement {
+            Statement::VarDecl { name, value } | Statement::ConstDecl { name, value, .. } => {
+                if is_boolean(value) {
+                    return Err(format!("Cannot store a comparison in '{}' yet", name));
+                }
+                // Concatenate straight into the variable so it keeps one buffer
+                if let Expression::BinaryOp { op, .. } = value
+                    && is_concat_op(op)
+                {
+                    let mut parts = Vec::new();
+                    self.concat_parts(value, &mut parts)?;
+                    self.concat_strings(name, parts);
+                } else {
+                    if let Expression::Variable(source) = value
+                        && source != name
+                    {
+                        self.share_buffer(source);
+                    }
+                    let value = self.compile_expression(value)?;
+                    self.store_value(name, value);
+                }
+            }
+            Statement::Print { expr } => {
+                if is_boolean(expr) {
+                    let cond = self.compile_int(expr)?;
+                    self.print_bool(cond);
+                } else {
+                    let value = self.compile_expression(expr)?;
+                    self.print_value(value);
+                }
+            }
+            Statement::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                let cond = self.compile_condition(condition)?;
+                let blocks = self.build_if(cond);
+                self.compile_program(then_block)?;
+                self.build_else(&blocks);
+                if let Some(else_block) = else_block {
+                    self.compile_program(else_block)?;
+                }
+                self.end_if(blocks);
+            }
+            // Only main is compiled so far, so returning from it is an exit
+            Statement::Return { expr } | Statement::Exit { expr } => {
+                let code = match expr {
+                    Some(expr) => self.compile_int(expr)?,
+                    None => self.const_integer(0),
+                };
+                self.finish_main_with_status(code);
+                return Ok(true);
+            }
+            Statement::FuncDef { .. } => {
+                return Err("Function compilation not yet implemented".to_string());
+            }
+        }
+        Ok(false)
+    }
+
+    /// Compile an expression to a string or integer value
+    pub fn compile_expression(&mut self, expr: &Expression) -> Result<RuntimeValue<'ctx>, String> {
+        match expr {
+            Expression::String(s) => Ok(RuntimeValue::String(self.intern_string(s))),
+            Expression::Variable(name) => self
+                .get_variable(name)
+                .ok_or_else(|| format!("Undefined variable '{}'", name)),
+            Expression::BinaryOp { op, .. } if is_concat_op(op) => {
+                let mut parts = Vec::new();
+                self.concat_parts(expr, &mut parts)?;
+                let name = self.gen_temp_name("concat");
+                Ok(RuntimeValue::String(self.concat_into(&name, &parts)))
+            }
+            _ => self.compile_int(expr).map(RuntimeValue::Integer),
+        }
+    }
+
+    /// Compile an integer expression; comparisons and logical operations yield an i1
+    pub fn compile_int(&mut self, expr: &Expression) -> Result<IntValue<'ctx>, String> {
+        match expr {
+            Expression::Number(n) => Ok(self.const_integer(*n)),
+            Expression::Variable(name) => match self.get_variable(name) {
+                Some(RuntimeValue::Integer(value)) => Ok(value),
+                _ => Err(format!("'{}' is not an integer variable", name)),
+            },
+            Expression::BinaryOp { op, left, right } if is_logical_op(op) => {
+                let left = self.compile_condition(left)?;
+                let right = self.compile_condition(right)?;
+                let value = match op.as_str() {
+                    "并" | "∧" => self.builder.build_and(left, right, "and"),
+                    "异或" => self.builder.build_xor(left, right, "xor"),
+                    "蕴含" => {
+                        let not_left = self.builder.build_not(left, "not").unwrap();
+                        self.builder.build_or(not_left, right, "implies")
+                    }
+                    _ => self.builder.build_or(left, right, "or"),
+                };
+                Ok(value.unwrap())
+            }
+            Expression::BinaryOp { op, left, right } if !is_concat_op(op) => {
+                let left = self.compile_int(left)?;
+                let right = self.compile_int(right)?;
+                if is_comparison_op(op) {
+                    Ok(self.build_comparison(op, left, right))
+                } else {
+                    Ok(self.build_arithmetic(op, left, right))
+                }
+            }
+            _ => Err(format!("Unsupported integer expression: {:?}", expr)),
+        }
+    }
+
+    /// Compile a branch condition: booleans as they are, integers as "not zero"
+    fn compile_condition(&mut self, expr: &Expression) -> Result<IntValue<'ctx>, String> {
+        let value = self.compile_int(expr)?;
+        if is_boolean(expr) {
+            return Ok(value);
+        }
+        let zero = self.const_integer(0);
+        Ok(self.build_comparison("!=", value, zero))
+    }
+
+    /// Flatten a chain of concatenations into the parts `concat_strings` takes
+    fn concat_parts(&self, expr: &Expression, parts: &mut Vec<StringPart>) -> Result<(), String> {
+        match expr {
+            Expression::BinaryOp { op, left, right } if is_concat_op(op) => {
+                self.concat_parts(left, parts)?;
+                self.concat_parts(right, parts)
+            }
+            Expression::String(s) => {
+                parts.push(StringPart::Literal(s.clone()));
+                Ok(())
+            }
+            Expression::Number(n) => {
+                parts.push(StringPart::Literal(n.to_string()));
+                Ok(())
+            }
+            Expression::Variable(name) if self.symbols.contains_key(name) => {
+                parts.push(StringPart::Variable(name.clone()));
+                Ok(())
+            }
+            Expression::Variable(name) => Err(format!("Undefined variable '{}'", name)),
+            _ => Err(format!("Cannot concatenate {:?} yet", expr)),
+        }
+    }
+
+    /// Get the textual LLVM IR of the module
+    pub fn llvm_ir(&self) -> String {
+        self.finalize_debug_info();
+        self.module.print_to_string().to_string()
+    }
+
+    /// Check the module is well-formed IR, e.g. every use dominated by its definition
+    pub fn verify(&self) -> Result<(), String> {
+        self.module.verify().map_err(|e| e.to_string())
+    }
+
     /// Output LLVM IR to file
     pub fn write_llvm_ir(&self, path: &str) {
+        self.finalize_debug_info();
         self.module.print_to_file(path).unwrap();
     }
 
@@ -564,6 +982,7 @@ impl<'ctx> Compiler<'ctx> {
             CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine,
         };
 
+        self.finalize_debug_info();
         Target::initialize_native(&InitializationConfig::default()).unwrap();
         let target_triple = TargetMachine::get_default_triple();
         let target = Target::from_triple(&target_triple).unwrap();
@@ -583,3 +1002,24 @@ impl<'ctx> Compiler<'ctx> {
             .unwrap();
     }
 }
+
+fn is_concat_op(op: &str) -> bool {
+    matches!(op, "~" | "⊕" | "⧺")
+}
+
+// ⊗ is left out: the grammar always reads it as multiplication
+fn is_comparison_op(op: &str) -> bool {
+    matches!(
+        op,
+        "==" | "⊙" | "≡" | "!=" | "≢" | "<" | "◁" | "⊲" | ">" | "▷" | "⊳"
+    )
+}
+
+fn is_logical_op(op: &str) -> bool {
+    matches!(op, "并" | "∧" | "或" | "∨" | "异或" | "蕴含")
+}
+
+// Comparisons and logical operations, which compile to an i1
+fn is_boolean(expr: &Expression) -> bool {
+    matches!(expr, Expression::BinaryOp { op, .. } if is_comparison_op(op) || is_logical_op(op))
+}
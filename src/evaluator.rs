@@ -1,122 +1,195 @@
-use crate::{Environment, Rule, Value, parser::parse_value};
+use crate::{
+    Environment, LingConfig, LingError, LingResult, Rule, Value, builtins,
+    executor::{ExecContext, call_function},
+    ling_number,
+    parser::{self, parse_value},
+    value::{FunctionBody, FunctionValue},
+};
 use pest::iterators::Pair;
+use std::num::IntErrorKind;
 
 /// Evaluate expressions with support for concatenation, arithmetic, and comparisons
-pub fn evaluate_expression(pair: Pair<Rule>, env: &Environment) -> Value {
+pub fn evaluate_expression(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    ctx.nested(pair.as_span().start(), |ctx| evaluate_pair(pair, env, ctx))
+}
+
+fn evaluate_pair(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     match pair.as_rule() {
-        Rule::EXPRESSION => evaluate_concat_expr(pair, env),
-        Rule::CONCAT_EXPR => evaluate_concat_expr(pair, env),
-        Rule::COMPARISON => evaluate_comparison(pair, env),
-        Rule::ADD_EXPR => evaluate_additive(pair, env),
-        Rule::MULT_EXPR => evaluate_multiplicative(pair, env),
-        Rule::PRIMARY => evaluate_primary(pair, env),
+        Rule::EXPRESSION => evaluate_range_expr(pair, env, ctx),
+        Rule::IMPLIES_EXPR | Rule::OR_EXPR | Rule::XOR_EXPR | Rule::AND_EXPR => {
+            evaluate_logical(pair, env, ctx)
+        }
+        Rule::NOT_EXPR => evaluate_not(pair, env, ctx),
+        Rule::CONCAT_EXPR => evaluate_concat_expr(pair, env, ctx),
+        Rule::COMPARISON => evaluate_comparison(pair, env, ctx),
+        Rule::ADD_EXPR => evaluate_additive(pair, env, ctx),
+        Rule::MULT_EXPR => evaluate_multiplicative(pair, env, ctx),
+        Rule::PRIMARY => evaluate_primary(pair, env, ctx),
         _ => parse_value(pair, env),
     }
 }
 
-/// Evaluate concatenation expressions (string concatenation)
-fn evaluate_concat_expr(pair: Pair<Rule>, env: &Environment) -> Value {
+/// Evaluate a top-level expression, building a range when it has a `…`
+/// clause; the step defaults to 1 and must be positive
+fn evaluate_range_expr(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     let mut parts = Vec::new();
-    let mut has_string = false;
-
     for inner in pair.into_inner() {
-        match inner.as_rule() {
-            Rule::CONCAT_OP => continue,
-            Rule::COMPARISON | Rule::ADD_EXPR | Rule::MULT_EXPR | Rule::TERM => {
-                let value = evaluate_expression(inner, env);
+        if !matches!(inner.as_rule(), Rule::RANGE_OP | Rule::STEP_KW) {
+            parts.push(evaluate_expression(inner, env, ctx)?);
+        }
+    }
 
-                match &value {
-                    Value::String(_) => has_string = true,
-                    _ => {}
-                }
+    if parts.len() == 1 {
+        return Ok(parts.pop().unwrap());
+    }
 
-                parts.push(value);
-            }
-            _ => {
-                let value = evaluate_expression(inner, env);
-                parts.push(value);
-            }
-        }
+    let mut bounds = parts.iter().map(|value| value.coerce_to_number(true));
+    let start = bounds.next().unwrap()?;
+    let end = bounds.next().unwrap()?;
+    let step = bounds.next().transpose()?.unwrap_or(1);
+    if step <= 0 {
+        return Err(LingError::InvalidOperation {
+            op: "步".to_string(),
+            left: "range".to_string(),
+            right: format!("step {}", step),
+        });
+    }
+
+    Ok(Value::Range { start, end, step })
+}
+
+/// Evaluate a 并, 或, 异或 or 蕴含 chain left to right, skipping operands once
+/// the result is decided; a lone operand is returned as-is, otherwise a
+/// boolean of the operands' truthiness
+fn evaluate_logical(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let mut inner = pair.into_inner();
+    let first = evaluate_expression(inner.next().unwrap(), env, ctx)?;
+    if inner.peek().is_none() {
+        return Ok(first);
     }
 
-    // If any part is a string, concatenate all as strings
-    if has_string || parts.is_empty() {
-        let result = parts
-            .iter()
-            .map(|v| match v {
-                Value::String(s) => s.clone(),
-                Value::Number(n) => n.to_string(),
-                Value::Boolean(b) => b.to_string(),
-                Value::Function(f) => format!("<function {}>", f.name),
-                Value::Void => String::new(),
-            })
-            .collect::<String>();
-        Value::String(result)
-    } else if parts.len() == 1 {
-        parts.into_iter().next().unwrap()
-    } else {
-        // Multiple numeric values without explicit operator - treat as string concat
-        let result = parts
-            .iter()
-            .map(|v| match v {
-                Value::Number(n) => n.to_string(),
-                Value::String(s) => s.clone(),
-                Value::Boolean(b) => b.to_string(),
-                Value::Function(f) => format!("<function {}>", f.name),
-                Value::Void => String::new(),
-            })
-            .collect::<String>();
-        Value::String(result)
+    let mut result = first.is_truthy();
+    while let (Some(op), Some(operand)) = (inner.next(), inner.next()) {
+        result = match op.as_rule() {
+            Rule::AND_OP if !result => false,
+            Rule::OR_OP if result => true,
+            Rule::IMPLIES_OP if !result => true,
+            Rule::XOR_OP => result != evaluate_expression(operand, env, ctx)?.is_truthy(),
+            _ => evaluate_expression(operand, env, ctx)?.is_truthy(),
+        };
     }
+    Ok(Value::Boolean(result))
 }
 
+/// Evaluate a negation: `¬ 甲` is true exactly when 甲 is falsy
+fn evaluate_not(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let operand = pair.into_inner().nth(1).unwrap();
+    let value = evaluate_expression(operand, env, ctx)?;
+    Ok(Value::Boolean(!value.is_truthy()))
+}
+
+/// Evaluate concatenation expressions. `⧺` always concatenates as strings
+/// (numbers are coerced via their display form); a lone operand is returned as-is
+fn evaluate_concat_expr(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let mut parts = Vec::new();
+
+    for inner in pair.into_inner() {
+        if inner.as_rule() == Rule::CONCAT_OP {
+            continue;
+        }
+        parts.push(evaluate_expression(inner, env, ctx)?);
+    }
+
+    if parts.len() == 1 {
+        return Ok(parts.pop().unwrap());
+    }
+
+    let result = parts.iter().map(|v| v.to_string()).collect::<String>();
+    Ok(Value::String(result))
+}
 
 /// Evaluate additive expressions (+ and -)
-fn evaluate_additive(pair: Pair<Rule>, env: &Environment) -> Value {
+fn evaluate_additive(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     let mut inner = pair.into_inner();
-    let mut result = evaluate_expression(inner.next().unwrap(), env);
+    let mut result = evaluate_expression(inner.next().unwrap(), env, ctx)?;
 
     while let Some(next) = inner.next() {
         match next.as_rule() {
             Rule::ADD_OP | Rule::SUB_OP => {
                 let operator = next.as_str();
-                let right = evaluate_expression(inner.next().unwrap(), env);
-                result = apply_arithmetic_op(operator, result, right);
+                let right = evaluate_expression(inner.next().unwrap(), env, ctx)?;
+                result = apply_arithmetic_op(operator, result, right, ctx.config())?;
             }
             _ => {
-                result = evaluate_expression(next, env);
+                result = evaluate_expression(next, env, ctx)?;
             }
         }
     }
 
-    result
+    Ok(result)
 }
 
 /// Evaluate multiplicative expressions (* and /)
-fn evaluate_multiplicative(pair: Pair<Rule>, env: &Environment) -> Value {
+fn evaluate_multiplicative(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     let mut inner = pair.into_inner();
-    let mut result = evaluate_expression(inner.next().unwrap(), env);
+    let mut result = evaluate_expression(inner.next().unwrap(), env, ctx)?;
 
     while let Some(next) = inner.next() {
         match next.as_rule() {
-            Rule::MUL_OP | Rule::DIV_OP => {
+            Rule::MUL_OP | Rule::DIV_OP | Rule::MOD_OP => {
                 let operator = next.as_str();
-                let right = evaluate_expression(inner.next().unwrap(), env);
-                result = apply_arithmetic_op(operator, result, right);
+                let right = evaluate_expression(inner.next().unwrap(), env, ctx)?;
+                result = apply_arithmetic_op(operator, result, right, ctx.config())?;
             }
             _ => {
-                result = evaluate_expression(next, env);
+                result = evaluate_expression(next, env, ctx)?;
             }
         }
     }
 
-    result
+    Ok(result)
 }
 
 /// Evaluate comparison expressions
-fn evaluate_comparison(pair: Pair<Rule>, env: &Environment) -> Value {
+fn evaluate_comparison(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     let mut inner = pair.into_inner();
-    let left = evaluate_expression(inner.next().unwrap(), env);
+    let left = evaluate_expression(inner.next().unwrap(), env, ctx)?;
 
     if let Some(op_pair) = inner.next() {
         if matches!(
@@ -124,18 +197,61 @@ fn evaluate_comparison(pair: Pair<Rule>, env: &Environment) -> Value {
             Rule::EQ_OP | Rule::NEQ_OP | Rule::LT_OP | Rule::GT_OP
         ) {
             let operator = op_pair.as_str();
-            let right = evaluate_expression(inner.next().unwrap(), env);
-            return apply_comparison_op(operator, left, right);
+            let right = evaluate_expression(inner.next().unwrap(), env, ctx)?;
+            return apply_comparison_op(operator, left, right, ctx.config());
         }
     }
 
-    left
+    Ok(left)
 }
 
-/// Evaluate primary expressions (literals, variables, parenthesized expressions)
-fn evaluate_primary(pair: Pair<Rule>, env: &Environment) -> Value {
-    let inner = pair.into_inner().next().unwrap();
+/// Evaluate primary expressions (literals, variables, parenthesized expressions),
+/// followed by any index suffixes
+fn evaluate_primary(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let mut inner = pair.into_inner().peekable();
+    let mut value = match inner.peek().map(|p| p.as_rule()) {
+        Some(Rule::PAREN_OPEN) => {
+            inner.next(); // Skip PAREN_OPEN
+            let value = evaluate_expression(inner.next().unwrap(), env, ctx)?;
+            inner.next(); // Skip PAREN_CLOSE
+            value
+        }
+        _ => evaluate_atom(inner.next().unwrap(), env, ctx)?,
+    };
+
+    for suffix in inner {
+        value = match suffix.as_rule() {
+            Rule::MEMBER => member(value, suffix.into_inner().as_str())?,
+            _ => evaluate_index(value, suffix, env, ctx)?,
+        };
+    }
 
+    Ok(value)
+}
+
+/// Look up `map.name`, the name standing for a string key
+fn member(target: Value, name: &str) -> LingResult<Value> {
+    match target {
+        Value::Map(mut entries) => entries
+            .remove(name)
+            .ok_or_else(|| LingError::RuntimeError("no such key".to_string())),
+        other => Err(LingError::TypeError {
+            expected: "map".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Evaluate the atomic part of a primary expression
+fn evaluate_atom(
+    inner: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     match inner.as_rule() {
         Rule::NUMBER => parse_number(inner.as_str()),
         Rule::STRING => {
@@ -150,40 +266,253 @@ fn evaluate_primary(pair: Pair<Rule>, env: &Environment) -> Value {
             } else {
                 s
             };
-            Value::String(content.to_string())
+            Ok(Value::String(content.to_string()))
         }
+        Rule::ARRAY_LITERAL => {
+            let mut items = Vec::new();
+            for item in inner.into_inner() {
+                if item.as_rule() == Rule::EXPRESSION {
+                    items.push(evaluate_expression(item, env, ctx)?);
+                }
+            }
+            Ok(Value::Array(items))
+        }
+        Rule::FUNC_CALL => evaluate_call(inner, env, ctx),
+        Rule::LAMBDA => evaluate_lambda(inner, env),
         Rule::VAR_NAME => {
             let var_name = inner.as_str();
-            env.get(var_name)
-                .unwrap_or_else(|| panic!("Undefined variable: {}", var_name))
+            let value = env
+                .get(var_name)
+                .ok_or_else(|| LingError::UndefinedVariable(var_name.to_string()))?;
+            if value == Value::Void && ctx.config().strict_mode {
+                return Err(LingError::RuntimeError(format!(
+                    "Variable '{}' used before assignment",
+                    var_name
+                )));
+            }
+            Ok(value)
         }
-        Rule::OPERATOR_SYMBOL => {
+        Rule::OPERATOR_LITERAL | Rule::OPERATOR_SYMBOL => {
             // Single operator as literal value
-            Value::Number(parse_operator_literal(inner.as_str()))
+            Ok(Value::Number(parse_operator_literal(inner.as_str())))
         }
         Rule::EXPRESSION
+        | Rule::IMPLIES_EXPR
+        | Rule::OR_EXPR
+        | Rule::XOR_EXPR
+        | Rule::AND_EXPR
+        | Rule::NOT_EXPR
         | Rule::CONCAT_EXPR
         | Rule::COMPARISON
         | Rule::ADD_EXPR
-        | Rule::MULT_EXPR => evaluate_expression(inner, env),
+        | Rule::MULT_EXPR => evaluate_expression(inner, env, ctx),
         _ => parse_value(inner, env),
     }
 }
 
+/// Evaluate a function call: 长度(⟦你好⟧)
+pub fn evaluate_call(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str();
+
+    let mut args = Vec::new();
+    for arg in inner {
+        if arg.as_rule() == Rule::EXPRESSION {
+            args.push(evaluate_expression(arg, env, ctx)?);
+        }
+    }
+
+    if let Some(builtin) = builtins::lookup(name) {
+        return builtin(&args);
+    }
+    if let Some(builtin) = builtins::lookup_higher_order(name) {
+        let mut apply = |callee: &Value, args: Vec<Value>| call_value(callee, args, env, ctx);
+        return builtin(&args, &mut apply);
+    }
+    if let Some(builtin) = builtins::lookup_configured(name) {
+        return builtin(&args, ctx.config());
+    }
+
+    match env.get(name) {
+        Some(callee @ Value::Function(_)) => call_value(&callee, args, env, ctx),
+        _ => Err(LingError::FunctionNotFound(name.to_string())),
+    }
+}
+
+/// Call a value that should be a function
+fn call_value(
+    callee: &Value,
+    args: Vec<Value>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    match callee {
+        Value::Function(func) => call_function(func, args, env, ctx),
+        other => Err(LingError::TypeError {
+            expected: "function".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Build an anonymous function from a lambda literal: ⦅ n ⦆ ⇒ n ⊠ ⊗.
+/// The body is kept as source and captures the enclosing locals by value
+fn evaluate_lambda(pair: Pair<Rule>, env: &Environment) -> LingResult<Value> {
+    let mut params = Vec::new();
+    let mut body = Vec::new();
+
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::VAR_NAME => params.push(inner.as_str().to_string()),
+            Rule::STATEMENT | Rule::EXPRESSION => body.push(inner.as_str().to_string()),
+            _ => {}
+        }
+    }
+
+    let body = FunctionBody::parse(&body.join("\n"))?;
+    let captured = env.capture_free_vars(&parser::body_free_variables(body.statements(), &params));
+    Ok(Value::Function(FunctionValue::new(
+        String::new(),
+        params,
+        body,
+        captured,
+    )))
+}
+
+/// Apply an index suffix (数组[⊕], 数组[第一] or 表[⟦键⟧]) to a value
+fn evaluate_index(
+    target: Value,
+    index: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let key = evaluate_index_key(index, env, ctx)?;
+
+    match target {
+        Value::Array(items) => {
+            let position = array_position(&key, items.len())?;
+            Ok(items.into_iter().nth(position).unwrap())
+        }
+        Value::Map(mut entries) => {
+            let key = map_key(&key)?;
+            entries
+                .remove(key)
+                .ok_or_else(|| LingError::RuntimeError(format!("Key not found: {}", key)))
+        }
+        other => Err(LingError::TypeError {
+            expected: "array".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Evaluate the key inside an index suffix; ordinals become 0-based numbers
+pub(crate) fn evaluate_index_key(
+    index: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    let index_pair = index
+        .into_inner()
+        .find(|p| matches!(p.as_rule(), Rule::ORDINAL | Rule::EXPRESSION))
+        .unwrap();
+
+    if index_pair.as_rule() == Rule::ORDINAL {
+        // Ordinals are 1-based: 第一 is the element at index 0
+        let ordinal = ling_number::parse_ordinal(index_pair.as_str()).ok_or_else(|| {
+            LingError::RuntimeError(format!("Invalid ordinal: {}", index_pair.as_str()))
+        })?;
+        return Ok(Value::Number(ordinal - 1));
+    }
+    evaluate_expression(index_pair, env, ctx)
+}
+
+/// Return a copy of `target` with the element at the `keys` path replaced
+/// by `value`; map keys that don't exist yet are inserted
+pub(crate) fn assign_index(target: Value, keys: &[Value], value: Value) -> LingResult<Value> {
+    let Some((key, rest)) = keys.split_first() else {
+        return Ok(value);
+    };
+
+    match target {
+        Value::Array(mut items) => {
+            let position = array_position(key, items.len())?;
+            let element = std::mem::replace(&mut items[position], Value::Void);
+            items[position] = assign_index(element, rest, value)?;
+            Ok(Value::Array(items))
+        }
+        Value::Map(mut entries) => {
+            let key = map_key(key)?.to_string();
+            let element = match entries.remove(&key) {
+                Some(element) => element,
+                None if rest.is_empty() => Value::Void,
+                None => return Err(LingError::RuntimeError(format!("Key not found: {}", key))),
+            };
+            entries.insert(key, assign_index(element, rest, value)?);
+            Ok(Value::Map(entries))
+        }
+        other => Err(LingError::TypeError {
+            expected: "array or map".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Check an index key against an array's length
+fn array_position(key: &Value, len: usize) -> LingResult<usize> {
+    let position = key.coerce_to_number(true)?;
+    usize::try_from(position)
+        .ok()
+        .filter(|&i| i < len)
+        .ok_or_else(|| {
+            LingError::RuntimeError(format!(
+                "Index {} out of bounds for array of length {}",
+                position, len
+            ))
+        })
+}
+
+/// Extract a string map key
+fn map_key(key: &Value) -> LingResult<&str> {
+    match key {
+        Value::String(s) => Ok(s),
+        other => Err(LingError::TypeError {
+            expected: "string".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
 /// Parse numbers (ASCII, Chinese, Alien, Operator-based)
-fn parse_number(s: &str) -> Value {
+fn parse_number(s: &str) -> LingResult<Value> {
+    let too_large = || LingError::ParseError("number too large".to_string());
+
     // Try ASCII number
-    if let Ok(n) = s.parse::<i64>() {
-        return Value::Number(n);
+    match s.parse::<i64>() {
+        Ok(n) => return Ok(Value::Number(n)),
+        Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow) => return Err(too_large()),
+        Err(_) => {}
     }
 
-    // Check for repeated operator numbers (⊕⊕⊕⊕⊕ = 5)
+    // Check for repeated operator numbers (⊕⊕⊕⊕⊕ = 5). Alien digits that
+    // double as operator literals (∅∄∃∀) are always positional: ∀∀ = 33
     if let Some(first_char) = s.chars().next() {
-        if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸∀∃∄∅".contains(first_char) {
+        if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸".contains(first_char) {
             let count = s.chars().take_while(|&c| c == first_char).count();
 
             if count == s.chars().count() {
-                return Value::Number(count as i64);
+                // One symbol reads as its literal value (⊙ = 5), two or more
+                // identical symbols as their count (⊙⊙ = 2)
+                let n = if count == 1 {
+                    parse_operator_literal(s)
+                } else {
+                    count as i64
+                };
+                return Ok(Value::Number(n));
             }
         }
     }
@@ -205,99 +534,23 @@ fn parse_number(s: &str) -> Value {
                 '℈' => 9,
                 _ => 0,
             };
-            result = result * 10 + digit;
+            result = result
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit))
+                .ok_or_else(too_large)?;
         }
-        return Value::Number(result);
+        return Ok(Value::Number(result));
     }
 
-    // Chinese numbers
-    Value::Number(parse_chinese_number(s))
-}
-
-/// Parse Chinese numerals to i64
-fn parse_chinese_number(s: &str) -> i64 {
-    let mut result = 0i64;
-    let mut current = 0i64;
-    let mut has_digit = false;
-
-    for ch in s.chars() {
-        match ch {
-            '零' | '〇' => {
-                has_digit = true;
-                current = 0;
-            }
-            '一' => {
-                has_digit = true;
-                current = 1;
-            }
-            '二' => {
-                has_digit = true;
-                current = 2;
-            }
-            '三' => {
-                has_digit = true;
-                current = 3;
-            }
-            '四' => {
-                has_digit = true;
-                current = 4;
-            }
-            '五' => {
-                has_digit = true;
-                current = 5;
-            }
-            '六' => {
-                has_digit = true;
-                current = 6;
-            }
-            '七' => {
-                has_digit = true;
-                current = 7;
-            }
-            '八' => {
-                has_digit = true;
-                current = 8;
-            }
-            '九' => {
-                has_digit = true;
-                current = 9;
-            }
-            '十' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result += current * 10;
-                current = 0;
-                has_digit = false;
-            }
-            '百' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result += current * 100;
-                current = 0;
-                has_digit = false;
-            }
-            '千' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result += current * 1000;
-                current = 0;
-                has_digit = false;
-            }
-            '万' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result = (result + current) * 10000;
-                current = 0;
-                has_digit = false;
-            }
-            _ => {}
-        }
+    // Chinese decimals (三点一四) and integers
+    if s.contains('点') {
+        return ling_number::chinese_to_float(s)
+            .map(Value::Float)
+            .ok_or_else(too_large);
     }
-    result + current
+    ling_number::chinese_to_number(s)
+        .map(Value::Number)
+        .ok_or_else(too_large)
 }
 
 /// Parse single operator symbols as numeric values
@@ -318,64 +571,102 @@ fn parse_operator_literal(op: &str) -> i64 {
         "∃" => 2,
         "∄" => 1,
         "∅" => 0,
+        "℧" => 4,
+        "℥" => 5,
+        "℞" => 6,
+        "℟" => 7,
+        "℣" => 8,
+        "℈" => 9,
         _ => 0,
     }
 }
 
 /// Apply arithmetic operations with alien operator support
-fn apply_arithmetic_op(operator: &str, left: Value, right: Value) -> Value {
-    let left_num = match left {
-        Value::Number(n) => n,
-        Value::String(s) => s.parse().unwrap_or(0),
-        Value::Boolean(b) => {
-            if b {
-                1
-            } else {
-                0
-            }
-        }
-        Value::Function(_) => panic!("Cannot use function in arithmetic"),
-        Value::Void => 0,
+fn apply_arithmetic_op(
+    operator: &str,
+    left: Value,
+    right: Value,
+    config: &LingConfig,
+) -> LingResult<Value> {
+    let invalid = || LingError::InvalidOperation {
+        op: operator.to_string(),
+        left: left.type_name().to_string(),
+        right: right.type_name().to_string(),
     };
 
-    let right_num = match right {
-        Value::Number(n) => n,
-        Value::String(s) => s.parse().unwrap_or(0),
-        Value::Boolean(b) => {
-            if b {
-                1
-            } else {
-                0
-            }
-        }
-        Value::Function(_) => panic!("Cannot use function in arithmetic"),
-        Value::Void => 0,
-    };
+    // A float on either side makes the whole operation floating point
+    if matches!(left, Value::Float(_)) || matches!(right, Value::Float(_)) {
+        let l = float_operand(&left, config.strict_mode).map_err(|_| invalid())?;
+        let r = float_operand(&right, config.strict_mode).map_err(|_| invalid())?;
+        let result = match operator {
+            "+" | "⊕" | "⊞" | "⨁" => l + r,
+            "-" | "⊟" | "⨂" => l - r,
+            "*" | "⊗" | "⊠" => l * r,
+            "/" | "⊘" | "⨸" | "%" | "⊛" if r == 0.0 => return Err(LingError::DivisionByZero),
+            "/" | "⊘" | "⨸" => l / r,
+            "%" | "⊛" => l % r,
+            _ => return Err(invalid()),
+        };
+        return Ok(Value::Float(result));
+    }
+
+    let left_num = left
+        .coerce_to_number(config.strict_mode)
+        .map_err(|_| invalid())?;
+
+    let right_num = right
+        .coerce_to_number(config.strict_mode)
+        .map_err(|_| invalid())?;
 
     let result = match operator {
-        "+" | "⊕" | "⊞" | "⨁" => left_num + right_num,
-        "-" | "⊟" | "⨂" => left_num - right_num,
-        "*" | "⊗" | "⊠" => left_num * right_num,
+        "+" | "⊕" | "⊞" | "⨁" => left_num.checked_add(right_num),
+        "-" | "⊟" | "⨂" => left_num.checked_sub(right_num),
+        "*" | "⊗" | "⊠" => left_num.checked_mul(right_num),
         "/" | "⊘" | "⨸" => {
             if right_num == 0 {
-                panic!("Division by zero");
+                return Err(LingError::DivisionByZero);
             }
-            left_num / right_num
+            // i64::MIN ⊘ 负一 has no remainder either; checked_div reports it
+            if config.float_division && left_num.checked_rem(right_num).is_some_and(|r| r != 0) {
+                return Ok(Value::Float(left_num as f64 / right_num as f64));
+            }
+            left_num.checked_div(right_num)
         }
-        "%" => {
+        "%" | "⊛" => {
             if right_num == 0 {
-                panic!("Modulo by zero");
+                return Err(LingError::DivisionByZero);
             }
-            left_num % right_num
+            left_num.checked_rem(right_num)
         }
-        _ => panic!("Unknown arithmetic operator: {}", operator),
+        _ => return Err(invalid()),
     };
 
-    Value::Number(result)
+    result
+        .map(Value::Number)
+        .ok_or_else(|| LingError::RuntimeError("integer overflow".to_string()))
+}
+
+/// A float operand as is, or any other value coerced like an integer one
+fn float_operand(value: &Value, strict: bool) -> LingResult<f64> {
+    match value {
+        Value::Float(f) => Ok(*f),
+        other => other.coerce_to_number(strict).map(|n| n as f64),
+    }
 }
 
 /// Apply comparison operations with alien operator support
-fn apply_comparison_op(operator: &str, left: Value, right: Value) -> Value {
+fn apply_comparison_op(
+    operator: &str,
+    left: Value,
+    right: Value,
+    config: &LingConfig,
+) -> LingResult<Value> {
+    let invalid = || LingError::InvalidOperation {
+        op: operator.to_string(),
+        left: left.type_name().to_string(),
+        right: right.type_name().to_string(),
+    };
+
     let result = match (&left, &right) {
         (Value::Number(l), Value::Number(r)) => match operator {
             "==" | "⊙" | "≡" => l == r,
@@ -384,7 +675,7 @@ fn apply_comparison_op(operator: &str, left: Value, right: Value) -> Value {
             ">" | "▷" | "⊳" => l > r,
             "<=" => l <= r,
             ">=" => l >= r,
-            _ => panic!("Unknown comparison operator: {}", operator),
+            _ => return Err(invalid()),
         },
         (Value::String(l), Value::String(r)) => match operator {
             "==" | "⊙" | "≡" => l == r,
@@ -393,37 +684,31 @@ fn apply_comparison_op(operator: &str, left: Value, right: Value) -> Value {
             ">" | "▷" | "⊳" => l > r,
             "<=" => l <= r,
             ">=" => l >= r,
-            _ => panic!("Unknown comparison operator: {}", operator),
+            _ => return Err(invalid()),
         },
+        (Value::Float(_), _) | (_, Value::Float(_)) => {
+            let l = float_operand(&left, config.strict_mode).map_err(|_| invalid())?;
+            let r = float_operand(&right, config.strict_mode).map_err(|_| invalid())?;
+            // Equal within `float_epsilon`, so 0.1 ⊞ 0.2 equals 0.3
+            let equal = l == r || (l - r).abs() <= config.float_epsilon;
+            match operator {
+                "==" | "⊙" | "≡" => equal,
+                "!=" | "⊗" | "≢" => !equal,
+                "<" | "◁" | "⊲" => l < r,
+                ">" | "▷" | "⊳" => l > r,
+                "<=" => l <= r,
+                ">=" => l >= r,
+                _ => return Err(invalid()),
+            }
+        }
         _ => {
-            // Try to convert both to numbers
-            let left_num = match left {
-                Value::Number(n) => n,
-                Value::String(s) => s.parse().unwrap_or(0),
-                Value::Boolean(b) => {
-                    if b {
-                        1
-                    } else {
-                        0
-                    }
-                }
-                Value::Function(_) => panic!("Cannot use function in comparison"),
-                Value::Void => 0,
-            };
+            let left_num = left
+                .coerce_to_number(config.strict_mode)
+                .map_err(|_| invalid())?;
 
-            let right_num = match right {
-                Value::Number(n) => n,
-                Value::String(s) => s.parse().unwrap_or(0),
-                Value::Boolean(b) => {
-                    if b {
-                        1
-                    } else {
-                        0
-                    }
-                }
-                Value::Function(_) => panic!("Cannot use function in comparison"),
-                Value::Void => 0,
-            };
+            let right_num = right
+                .coerce_to_number(config.strict_mode)
+                .map_err(|_| invalid())?;
 
             match operator {
                 "==" | "⊙" | "≡" => left_num == right_num,
@@ -432,28 +717,32 @@ fn apply_comparison_op(operator: &str, left: Value, right: Value) -> Value {
                 ">" | "▷" | "⊳" => left_num > right_num,
                 "<=" => left_num <= right_num,
                 ">=" => left_num >= right_num,
-                _ => panic!("Unknown comparison operator: {}", operator),
+                _ => return Err(invalid()),
             }
         }
     };
 
-    Value::Boolean(result)
+    Ok(Value::Boolean(result))
 }
 
 /// Evaluate a term (for backward compatibility)
-pub fn evaluate_term(pair: Pair<Rule>, env: &Environment) -> Value {
+pub fn evaluate_term(
+    pair: Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
     match pair.as_rule() {
         Rule::NUMBER => parse_number(pair.as_str()),
         Rule::STRING => {
             let s = pair.as_str();
             let content = &s[1..s.len() - 1]; // Remove quotes
-            Value::String(content.to_string())
+            Ok(Value::String(content.to_string()))
         }
         Rule::VAR_NAME => {
             let var_name = pair.as_str();
             env.get(var_name)
-                .unwrap_or_else(|| panic!("Undefined variable: {}", var_name))
+                .ok_or_else(|| LingError::UndefinedVariable(var_name.to_string()))
         }
-        _ => evaluate_expression(pair, env),
+        _ => evaluate_expression(pair, env, ctx),
     }
 }
@@ -1,44 +1,825 @@
-use crate::{Environment, Rule, Value, evaluator::evaluate_expression};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
 
-pub fn execute_program(pair: pest::iterators::Pair<Rule>, env: &mut Environment) {
-    match pair.as_rule() {
-        Rule::PROGRAM => {
-            for inner_pair in pair.into_inner() {
-                execute_program(inner_pair, env);
+use pest::iterators::{Pair, Pairs};
+
+use crate::{
+    Environment, LingConfig, LingError, LingResult, Rule, Value,
+    evaluator::{assign_index, evaluate_call, evaluate_expression, evaluate_index_key},
+    parser::{self, Span},
+    utils::values_equal_within,
+    value::{FunctionBody, FunctionValue},
+};
+
+/// How control leaves a statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum Flow {
+    /// Carry on with the next statement; holds the statement's value
+    Normal(Value),
+    /// Leave the enclosing function with a value
+    Return(Value),
+    /// Stop the whole program with the given status
+    Exit(i64),
+    /// Leave the innermost loop, or the loop with the given label
+    Break(Option<String>),
+    /// Start the next pass of the innermost loop, or of the labeled one
+    Continue(Option<String>),
+}
+
+/// What a loop does once a pass of its body has finished with `flow`
+enum LoopStep {
+    Next,
+    Stop,
+    Leave(Flow),
+}
+
+impl LoopStep {
+    /// Decide for the loop named `label`; jumps aimed at another label pass through
+    fn after(flow: Flow, label: Option<&str>) -> Self {
+        let targets_us = |target: &Option<String>| match target {
+            None => true,
+            Some(target) => label == Some(target.as_str()),
+        };
+        match flow {
+            Flow::Normal(_) => LoopStep::Next,
+            Flow::Continue(target) if targets_us(&target) => LoopStep::Next,
+            Flow::Break(target) if targets_us(&target) => LoopStep::Stop,
+            flow => LoopStep::Leave(flow),
+        }
+    }
+}
+
+/// Whether a statement is a bare expression or call, whose value a program
+/// reports, rather than a declaration, print or control statement
+pub(crate) fn is_expression_statement(pair: &pest::iterators::Pair<Rule>) -> bool {
+    pair.as_rule() == Rule::STATEMENT
+        && pair
+            .clone()
+            .into_inner()
+            .next()
+            .is_some_and(|inner| matches!(inner.as_rule(), Rule::EXPRESSION | Rule::FUNC_CALL))
+}
+
+/// The error for a break or continue that no enclosing loop answered
+pub(crate) fn stray_jump(label: Option<String>) -> LingError {
+    match label {
+        Some(label) => LingError::RuntimeError(format!("Unknown loop label: {}", label)),
+        None => LingError::RuntimeError("Break or continue outside of a loop".to_string()),
+    }
+}
+
+/// The name of a loop's label, consuming it if it leads `inner`
+fn loop_label(inner: &mut Pairs<Rule>) -> Option<String> {
+    let label = inner.peek().filter(|p| p.as_rule() == Rule::LOOP_LABEL)?;
+    inner.next();
+    Some(label_name(&label))
+}
+
+/// A LOOP_LABEL's name without its leading @
+fn label_name(label: &Pair<Rule>) -> String {
+    label.as_str().trim_start_matches('@').to_string()
+}
+
+/// Results of memoized function calls, keyed by function identity and arguments
+pub type MemoCache = HashMap<(FunctionValue, Vec<Value>), Value>;
+
+/// Host hook that renders printed values in place of their `Display` form
+#[derive(Clone)]
+pub struct ValueFormatter(Rc<dyn Fn(&Value) -> String>);
+
+impl ValueFormatter {
+    /// Wrap a host formatting function
+    pub fn new(format: impl Fn(&Value) -> String + 'static) -> Self {
+        ValueFormatter(Rc::new(format))
+    }
+
+    /// Render a value the way the host wants it printed
+    pub fn format(&self, value: &Value) -> String {
+        (self.0)(value)
+    }
+}
+
+impl fmt::Debug for ValueFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ValueFormatter")
+    }
+}
+
+/// A side effect a dry run would have performed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// A variable declared or assigned
+    Write { name: String, value: Value },
+    /// A line printed to program output
+    Print(String),
+    /// A line printed to the diagnostics stream
+    ErrorPrint(String),
+}
+
+/// Per-run executor state: where output goes and the limits to enforce
+pub struct ExecContext<'a> {
+    out: &'a mut dyn Write,
+    config: LingConfig,
+    output_bytes: usize,
+    call_depth: usize,
+    walk_depth: usize,
+    // Exit requested from inside a function call, raised at the next statement
+    pending_exit: Option<i64>,
+    memo: MemoCache,
+    formatter: Option<ValueFormatter>,
+    // Where 错误输出 writes; stderr when unset
+    err_out: Option<&'a mut dyn Write>,
+    // Set during a dry run: prints are recorded here instead of written
+    effects: Option<Vec<Effect>>,
+    // Set when tracking coverage: statements run outside function calls
+    covered: Option<BTreeSet<Span>>,
+    // Offset of the innermost pair a pending error surfaced from
+    error_at: Option<usize>,
+}
+
+impl<'a> ExecContext<'a> {
+    /// Create a context writing program output to `out`
+    pub fn new(out: &'a mut dyn Write, config: LingConfig) -> Self {
+        ExecContext {
+            out,
+            config,
+            output_bytes: 0,
+            call_depth: 0,
+            walk_depth: 0,
+            pending_exit: None,
+            memo: MemoCache::new(),
+            formatter: None,
+            err_out: None,
+            effects: None,
+            covered: None,
+            error_at: None,
+        }
+    }
+
+    /// Record the span of every statement that runs outside a function call.
+    /// Function bodies run from a copy of their source, so their statements
+    /// have no span in the program
+    pub fn track_coverage(mut self) -> Self {
+        self.covered = Some(BTreeSet::new());
+        self
+    }
+
+    /// Take the spans of the statements run so far, relative to the parsed
+    /// source
+    pub fn take_coverage(&mut self) -> BTreeSet<Span> {
+        self.covered
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Record variable writes and prints as `Effect`s, printing nothing
+    pub fn dry_run(mut self) -> Self {
+        self.effects = Some(Vec::new());
+        self
+    }
+
+    /// Take the effects recorded so far in a dry run
+    pub fn take_effects(&mut self) -> Vec<Effect> {
+        self.effects
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Note a variable write when dry-running
+    fn record_write(&mut self, name: &str, value: &Value) {
+        if let Some(effects) = &mut self.effects {
+            effects.push(Effect::Write {
+                name: name.to_string(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    /// Send diagnostic prints to `err_out` instead of stderr
+    pub fn with_err_out(mut self, err_out: &'a mut dyn Write) -> Self {
+        self.err_out = Some(err_out);
+        self
+    }
+
+    /// Print values through `formatter` instead of `Display`
+    pub fn with_formatter(mut self, formatter: Option<ValueFormatter>) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// Reuse memoized results from earlier runs
+    pub fn with_memo(mut self, memo: MemoCache) -> Self {
+        self.memo = memo;
+        self
+    }
+
+    /// Hand back the memoized results so a later run can reuse them
+    pub fn into_memo(self) -> MemoCache {
+        self.memo
+    }
+
+    /// Count output already written by earlier runs against the same limit
+    pub fn with_output_bytes(mut self, written: usize) -> Self {
+        self.output_bytes = written;
+        self
+    }
+
+    /// Get the configuration this run executes under
+    pub fn config(&self) -> &LingConfig {
+        &self.config
+    }
+
+    /// Offset in the parsed source of the innermost pair the last error
+    /// surfaced from. Errors inside function calls point at the call, since
+    /// bodies run from a copy of their source
+    pub fn error_at(&self) -> Option<usize> {
+        self.error_at
+    }
+
+    /// Run `walk` over the pair starting at `start`, one level deeper in a
+    /// parse-tree walk, failing with `StackOverflow` past `max_parse_depth`
+    /// instead of exhausting the stack
+    pub(crate) fn nested<T>(
+        &mut self,
+        start: usize,
+        walk: impl FnOnce(&mut Self) -> LingResult<T>,
+    ) -> LingResult<T> {
+        if self.walk_depth >= self.config.max_parse_depth {
+            return Err(LingError::StackOverflow(self.config.max_parse_depth));
+        }
+        self.walk_depth += 1;
+        let result = walk(self);
+        self.walk_depth -= 1;
+        if self.call_depth == 0 {
+            // An error propagates up through every enclosing pair, so the
+            // first one to fail is the innermost. Success means any earlier
+            // error was handled
+            match result {
+                Ok(_) => self.error_at = None,
+                Err(_) => {
+                    self.error_at.get_or_insert(start);
+                }
             }
         }
-        Rule::STATEMENT => {
+        result
+    }
+
+    /// Count one loop pass, failing once a loop runs more than
+    /// `max_recursion_depth` times
+    fn count_iteration(&self, iterations: &mut usize) -> LingResult<()> {
+        *iterations += 1;
+        if *iterations > self.config.max_recursion_depth {
+            return Err(LingError::RuntimeError(format!(
+                "Loop exceeded {} iterations",
+                self.config.max_recursion_depth
+            )));
+        }
+        Ok(())
+    }
+
+    /// Write one line of program output, enforcing `max_output_bytes`
+    fn write_line(&mut self, line: &str) -> LingResult<()> {
+        if let Some(effects) = &mut self.effects {
+            effects.push(Effect::Print(line.to_string()));
+            return Ok(());
+        }
+        let bytes = line.len() + 1;
+        if let Some(limit) = self.config.max_output_bytes
+            && self.output_bytes + bytes > limit
+        {
+            // Once over the limit, stop accepting output for the rest of the run
+            self.output_bytes = limit + 1;
+            return Err(LingError::RuntimeError("output limit exceeded".to_string()));
+        }
+        self.output_bytes += bytes;
+        writeln!(self.out, "{}", line).map_err(|e| LingError::IOError(e.to_string()))
+    }
+
+    /// Write one line of diagnostics, which doesn't count towards the output limit
+    fn write_err_line(&mut self, line: &str) -> LingResult<()> {
+        if let Some(effects) = &mut self.effects {
+            effects.push(Effect::ErrorPrint(line.to_string()));
+            return Ok(());
+        }
+        let written = match &mut self.err_out {
+            Some(err_out) => writeln!(err_out, "{}", line),
+            None => writeln!(io::stderr(), "{}", line),
+        };
+        written.map_err(|e| LingError::IOError(e.to_string()))
+    }
+}
+
+/// Peel `VALUE` wrappers off an expression pair, returning the wrapped
+/// expression (or the pair itself when it is already an expression or terminal)
+pub fn unwrap_value(pair: Pair<Rule>) -> LingResult<Pair<Rule>> {
+    if pair.as_rule() != Rule::VALUE {
+        return Ok(pair);
+    }
+    match pair.into_inner().next() {
+        Some(inner) => unwrap_value(inner),
+        None => Err(LingError::ParseError("Empty value".to_string())),
+    }
+}
+
+/// Pair the names of a declaration with its value: a single name takes the
+/// whole value, several take the elements of an array of the same length
+pub fn destructure(names: Vec<String>, value: Value) -> LingResult<Vec<(String, Value)>> {
+    if let [name] = names.as_slice() {
+        return Ok(vec![(name.clone(), value)]);
+    }
+    match value {
+        Value::Array(items) if items.len() == names.len() => {
+            Ok(names.into_iter().zip(items).collect())
+        }
+        Value::Array(items) => Err(LingError::RuntimeError(format!(
+            "Cannot destructure {} values into {} names",
+            items.len(),
+            names.len()
+        ))),
+        other => Err(LingError::TypeError {
+            expected: "array".to_string(),
+            found: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Execute a program, statement or expression pair, returning the value of the
+/// last expression statement (or `Value::Void` for declarations and prints).
+/// Output goes to stdout with the default configuration. An exit statement
+/// ends the program early, and its status is returned as a `Value::Number`.
+pub fn execute_program(
+    pair: pest::iterators::Pair<Rule>,
+    env: &mut Environment,
+) -> LingResult<Value> {
+    let mut stdout = io::stdout();
+    let mut ctx = ExecContext::new(&mut stdout, LingConfig::default());
+    match execute(pair, env, &mut ctx)? {
+        Flow::Normal(value) | Flow::Return(value) => Ok(value),
+        Flow::Exit(status) => Ok(Value::Number(status)),
+        Flow::Break(label) | Flow::Continue(label) => Err(stray_jump(label)),
+    }
+}
+
+/// Execute a pair against an explicit context (output sink and limits)
+pub fn execute(
+    pair: pest::iterators::Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Flow> {
+    ctx.nested(pair.as_span().start(), |ctx| execute_pair(pair, env, ctx))
+}
+
+fn execute_pair(
+    pair: pest::iterators::Pair<Rule>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Flow> {
+    match pair.as_rule() {
+        Rule::PROGRAM | Rule::STATEMENT => {
+            if let Some(covered) = &mut ctx.covered
+                && pair.as_rule() == Rule::STATEMENT
+                && ctx.call_depth == 0
+            {
+                let span = pair.as_span();
+                covered.insert(Span {
+                    start: span.start(),
+                    end: span.end(),
+                });
+            }
+            let is_program = pair.as_rule() == Rule::PROGRAM;
+            let mut last = Value::Void;
             for inner_pair in pair.into_inner() {
-                execute_program(inner_pair, env);
+                if matches!(inner_pair.as_rule(), Rule::EOI | Rule::STMT_END) {
+                    continue;
+                }
+                // A program's value comes from its last expression statement,
+                // so a trailing declaration or print does not hide it
+                let keep = !is_program || is_expression_statement(&inner_pair);
+                match execute(inner_pair, env, ctx)? {
+                    Flow::Normal(value) if keep => last = value,
+                    Flow::Normal(_) => {}
+                    flow => return Ok(flow),
+                }
+                if let Some(status) = ctx.pending_exit.take() {
+                    return Ok(Flow::Exit(status));
+                }
             }
+            Ok(Flow::Normal(last))
         }
         Rule::VAR_DECL => {
             let mut inner = pair.into_inner();
-            inner.next(); // Skip LET_KW
-            let var_name = inner.next().unwrap().as_str().to_string();
-            let value_pair = inner.next().unwrap();
+            let is_const = inner.next().unwrap().as_rule() == Rule::CONST_KW;
+            let mut names = Vec::new();
+            let mut initializer = None;
+            for part in inner {
+                match part.as_rule() {
+                    Rule::VAR_NAME => names.push(part.as_str().to_string()),
+                    Rule::EXPRESSION => initializer = Some(part),
+                    _ => {}
+                }
+            }
+            // Without an initializer the variables hold Void until assigned
+            let bindings = match initializer {
+                Some(expr) => {
+                    destructure(names, evaluate_expression(unwrap_value(expr)?, env, ctx)?)?
+                }
+                None if is_const => {
+                    return Err(LingError::RuntimeError(format!(
+                        "Constant '{}' needs a value",
+                        names[0]
+                    )));
+                }
+                None => names.into_iter().map(|name| (name, Value::Void)).collect(),
+            };
+            for (name, value) in bindings {
+                ctx.record_write(&name, &value);
+                env.set_with_mutability(name, value, !is_const);
+            }
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::ASSIGN_STMT => {
+            let mut inner = pair.into_inner();
+            let var_name = inner.next().unwrap().as_str();
+            let mut keys = Vec::new();
+            let mut value = Value::Void;
+            for part in inner {
+                match part.as_rule() {
+                    Rule::INDEX => keys.push(evaluate_index_key(part, env, ctx)?),
+                    Rule::EXPRESSION => value = evaluate_expression(part, env, ctx)?,
+                    _ => {}
+                }
+            }
 
-            // Extract EXPRESSION from VALUE
-            let expr_pair = value_pair.into_inner().next().unwrap();
-            let value = evaluate_expression(expr_pair, env);
-            env.set(var_name, value);
+            match env.is_mutable(var_name) {
+                None => return Err(LingError::UndefinedVariable(var_name.to_string())),
+                Some(false) => return Err(LingError::ImmutableAssignment(var_name.to_string())),
+                Some(true) => {}
+            }
+
+            // Values are immutable: rebuild the collection and store it back
+            let current = env.get(var_name).unwrap();
+            let updated = assign_index(current, &keys, value)?;
+            ctx.record_write(var_name, &updated);
+            env.update(var_name, updated)
+                .map_err(LingError::RuntimeError)?;
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::PRINT_STMT | Rule::ERR_PRINT_STMT => {
+            let to_stderr = pair.as_rule() == Rule::ERR_PRINT_STMT;
+            let mut parts = Vec::new();
+            for expr_pair in pair
+                .into_inner()
+                .filter(|p| p.as_rule() == Rule::EXPRESSION)
+            {
+                let value = evaluate_expression(unwrap_value(expr_pair)?, env, ctx)?;
+                if value != Value::Void {
+                    parts.push(match &ctx.formatter {
+                        Some(formatter) => formatter.format(&value),
+                        None => value.to_string(),
+                    });
+                }
+            }
+            if !parts.is_empty() {
+                let line = parts.join(" ");
+                if to_stderr {
+                    ctx.write_err_line(&line)?;
+                } else {
+                    ctx.write_line(&line)?;
+                }
+            }
+            Ok(Flow::Normal(Value::Void))
         }
-        Rule::PRINT_STMT => {
+        Rule::WHILE_STMT => {
             let mut inner = pair.into_inner();
-            inner.next(); // Skip PRINT_KW
-            let value_pair = inner.next().unwrap();
+            let label = loop_label(&mut inner);
+            inner.next(); // Skip WHILE_KW
+            let cond_pair = inner.find(|p| p.as_rule() == Rule::EXPRESSION).unwrap();
+            let body: Vec<_> = inner.filter(|p| p.as_rule() == Rule::STATEMENT).collect();
 
-            // Extract EXPRESSION from VALUE
-            let expr_pair = value_pair.into_inner().next().unwrap();
-            let value = evaluate_expression(expr_pair, env);
-            match value {
-                Value::Number(n) => println!("{}", n),
-                Value::String(s) => println!("{}", s),
-                Value::Boolean(b) => println!("{}", b),
-                Value::Function(f) => println!("<function {}>", f.name),
-                Value::Void => {}
+            let mut iterations = 0;
+            while evaluate_expression(cond_pair.clone(), env, ctx)?.is_truthy() {
+                ctx.count_iteration(&mut iterations)?;
+                match LoopStep::after(execute_block(&body, env, ctx)?, label.as_deref()) {
+                    LoopStep::Next => {}
+                    LoopStep::Stop => break,
+                    LoopStep::Leave(flow) => return Ok(flow),
+                }
             }
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::DO_WHILE_STMT => {
+            let mut label = None;
+            let mut body = Vec::new();
+            let mut cond_pair = None;
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::LOOP_LABEL => label = Some(label_name(&inner)),
+                    Rule::STATEMENT => body.push(inner),
+                    Rule::EXPRESSION => cond_pair = Some(inner),
+                    _ => {}
+                }
+            }
+            let cond_pair = cond_pair.unwrap();
+
+            let mut iterations = 0;
+            loop {
+                ctx.count_iteration(&mut iterations)?;
+                match LoopStep::after(execute_block(&body, env, ctx)?, label.as_deref()) {
+                    LoopStep::Next => {}
+                    LoopStep::Stop => break,
+                    LoopStep::Leave(flow) => return Ok(flow),
+                }
+                if !evaluate_expression(cond_pair.clone(), env, ctx)?.is_truthy() {
+                    break;
+                }
+            }
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::FOR_STMT => {
+            let mut inner = pair.into_inner();
+            let label = loop_label(&mut inner);
+            inner.next(); // Skip FOR_KW
+            let var_name = inner.next().unwrap().as_str().to_string();
+            let iter_pair = inner.find(|p| p.as_rule() == Rule::EXPRESSION).unwrap();
+            let body: Vec<_> = inner.filter(|p| p.as_rule() == Rule::STATEMENT).collect();
+
+            let iterable = evaluate_expression(iter_pair, env, ctx)?;
+            let items = iterable.elements().ok_or_else(|| LingError::TypeError {
+                expected: "array or range".to_string(),
+                found: iterable.type_name().to_string(),
+            })?;
+            // Ranges are walked lazily, and each pass counts towards the loop cap
+            let mut iterations = 0;
+            for item in items {
+                ctx.count_iteration(&mut iterations)?;
+                // The loop variable lives in its own scope around the body's
+                env.push_scope();
+                env.set(var_name.clone(), item);
+                let flow = execute_block(&body, env, ctx);
+                env.pop_scope();
+                match LoopStep::after(flow?, label.as_deref()) {
+                    LoopStep::Next => {}
+                    LoopStep::Stop => break,
+                    LoopStep::Leave(flow) => return Ok(flow),
+                }
+            }
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::FUNC_DEF => {
+            let mut name = None;
+            let mut params = Vec::new();
+            let mut body = Vec::new();
+            let mut memoized = false;
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    // Other attributes are accepted but have no effect yet
+                    Rule::MEMO_KW => memoized = true,
+                    Rule::ATTRIBUTE if inner.as_str() == "@记忆" => memoized = true,
+                    Rule::VAR_NAME if name.is_none() => name = Some(inner.as_str().to_string()),
+                    Rule::VAR_NAME => params.push(inner.as_str().to_string()),
+                    Rule::STATEMENT => body.push(inner.as_str().to_string()),
+                    _ => {}
+                }
+            }
+            let name = name.unwrap();
+
+            let body = FunctionBody::parse(&body.join("\n"))?;
+            let captured =
+                env.capture_free_vars(&parser::body_free_variables(body.statements(), &params));
+            let mut func = FunctionValue::new(name.clone(), params, body, captured);
+            func.memoized = memoized;
+            env.set(name, Value::Function(func));
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::RETURN_STMT => {
+            let mut values = Vec::new();
+            for expr_pair in pair
+                .into_inner()
+                .filter(|p| p.as_rule() == Rule::EXPRESSION)
+            {
+                values.push(evaluate_expression(expr_pair, env, ctx)?);
+            }
+            let value = match values.len() {
+                0 => Value::Void,
+                1 => values.pop().unwrap(),
+                _ => Value::Array(values),
+            };
+            Ok(Flow::Return(value))
+        }
+        Rule::EXIT_STMT => {
+            let status = match pair.into_inner().find(|p| p.as_rule() == Rule::EXPRESSION) {
+                Some(expr_pair) => {
+                    let value = evaluate_expression(expr_pair, env, ctx)?;
+                    value.coerce_to_number(true)?
+                }
+                None => 0,
+            };
+            Ok(Flow::Exit(status))
+        }
+        Rule::IF_STMT => execute(pair.into_inner().next().unwrap(), env, ctx),
+        Rule::ALIEN_IF_STMT | Rule::TRAD_IF_STMT => {
+            let mut condition = None;
+            let mut then_body = Vec::new();
+            let mut else_body = None;
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::EXPRESSION if condition.is_none() => condition = Some(inner),
+                    Rule::ELSE_KW => else_body = Some(Vec::new()),
+                    Rule::STATEMENT => match else_body.as_mut() {
+                        Some(else_body) => else_body.push(inner),
+                        None => then_body.push(inner),
+                    },
+                    _ => {}
+                }
+            }
+
+            if evaluate_expression(condition.unwrap(), env, ctx)?.is_truthy() {
+                execute_block(&then_body, env, ctx)
+            } else {
+                execute_block(&else_body.unwrap_or_default(), env, ctx)
+            }
+        }
+        Rule::TRY_STMT => {
+            let mut try_body = Vec::new();
+            let mut error_name = None;
+            let mut catch_body = Vec::new();
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::VAR_NAME => error_name = Some(inner.as_str().to_string()),
+                    Rule::STATEMENT if error_name.is_none() => try_body.push(inner),
+                    Rule::STATEMENT => catch_body.push(inner),
+                    _ => {}
+                }
+            }
+
+            // Only errors are caught; returns, breaks and exits arrive as Ok.
+            // Sandbox limits pass through, so 尝试 can't be used to escape them
+            match execute_block(&try_body, env, ctx) {
+                Err(error) if !error.is_resource_limit() => {
+                    env.push_scope();
+                    env.set(error_name.unwrap(), Value::String(error.to_string()));
+                    let flow = execute_block(&catch_body, env, ctx);
+                    env.pop_scope();
+                    flow
+                }
+                flow => flow,
+            }
+        }
+        Rule::SWITCH_STMT => {
+            let mut fall_through = false;
+            let mut subject = Value::Void;
+            let mut arms = Vec::new();
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::FALLTHROUGH_KW => fall_through = true,
+                    Rule::EXPRESSION => subject = evaluate_expression(inner, env, ctx)?,
+                    Rule::CASE_ARM | Rule::DEFAULT_ARM => arms.push(inner),
+                    _ => {}
+                }
+            }
+
+            // Case values are evaluated in order, up to the first match
+            let mut matched = None;
+            'arms: for (index, arm) in arms.iter().enumerate() {
+                if arm.as_rule() == Rule::DEFAULT_ARM {
+                    matched = Some(index);
+                    break;
+                }
+                for case in arm.clone().into_inner() {
+                    if case.as_rule() == Rule::EXPRESSION
+                        && values_equal_within(
+                            &evaluate_expression(case, env, ctx)?,
+                            &subject,
+                            ctx.config().float_epsilon,
+                        )
+                    {
+                        matched = Some(index);
+                        break 'arms;
+                    }
+                }
+            }
+
+            // An unlabeled break leaves the switch rather than an enclosing loop
+            for arm in &arms[matched.unwrap_or(arms.len())..] {
+                let body: Vec<_> = arm
+                    .clone()
+                    .into_inner()
+                    .filter(|p| p.as_rule() == Rule::STATEMENT)
+                    .collect();
+                match execute_block(&body, env, ctx)? {
+                    Flow::Normal(_) if fall_through => {}
+                    Flow::Normal(_) | Flow::Break(None) => break,
+                    flow => return Ok(flow),
+                }
+            }
+            Ok(Flow::Normal(Value::Void))
+        }
+        Rule::BREAK_STMT | Rule::CONTINUE_STMT => {
+            let is_break = pair.as_rule() == Rule::BREAK_STMT;
+            let label = pair
+                .into_inner()
+                .find(|p| p.as_rule() == Rule::LOOP_LABEL)
+                .map(|p| label_name(&p));
+            Ok(if is_break {
+                Flow::Break(label)
+            } else {
+                Flow::Continue(label)
+            })
+        }
+        Rule::FUNC_CALL => evaluate_call(pair, env, ctx).map(Flow::Normal),
+        Rule::EXPRESSION => evaluate_expression(pair, env, ctx).map(Flow::Normal),
+        _ => Ok(Flow::Normal(Value::Void)),
+    }
+}
+
+/// Execute a block's statements in a fresh scope, stopping early on exit
+fn execute_block(
+    statements: &[pest::iterators::Pair<Rule>],
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Flow> {
+    env.push_scope();
+    let mut result = Ok(Flow::Normal(Value::Void));
+    for statement in statements {
+        result = execute(statement.clone(), env, ctx);
+        if !matches!(result, Ok(Flow::Normal(_))) {
+            break;
+        }
+    }
+    env.pop_scope();
+    result
+}
+
+/// Call a user function: bind arguments in a fresh function scope seeded with
+/// its closure, run the body, and yield the returned (or last) value.
+/// Memoized functions answer repeated argument values from the cache
+pub fn call_function(
+    func: &FunctionValue,
+    args: Vec<Value>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    if args.len() != func.arity() {
+        return Err(LingError::ArgumentMismatch {
+            expected: func.arity(),
+            found: args.len(),
+        });
+    }
+    let args = [func.bound.as_slice(), &args].concat();
+    if func.memoized {
+        for arg in &args {
+            arg.ensure_hashable()?;
+        }
+        let key = (func.clone(), args.clone());
+        if let Some(value) = ctx.memo.get(&key) {
+            return Ok(value.clone());
+        }
+        let value = run_function(func, args, env, ctx)?;
+        // A call cut short by an exit has no result worth remembering
+        if ctx.pending_exit.is_none() {
+            ctx.memo.insert(key, value.clone());
+        }
+        return Ok(value);
+    }
+    run_function(func, args, env, ctx)
+}
+
+/// Run a function body for `call_function`, bypassing the memo cache
+fn run_function(
+    func: &FunctionValue,
+    args: Vec<Value>,
+    env: &mut Environment,
+    ctx: &mut ExecContext,
+) -> LingResult<Value> {
+    if ctx.call_depth >= ctx.config.max_recursion_depth {
+        return Err(LingError::RuntimeError(format!(
+            "Maximum recursion depth {} exceeded",
+            ctx.config.max_recursion_depth
+        )));
+    }
+
+    env.push_function_scope(func.captured.clone());
+    for (param, arg) in func.params.iter().zip(args) {
+        env.set(param.clone(), arg);
+    }
+    ctx.call_depth += 1;
+    let mut result = Ok(Flow::Normal(Value::Void));
+    for program in func.body.statements() {
+        result = execute(program, env, ctx);
+    }
+    ctx.call_depth -= 1;
+    env.pop_scope();
+
+    match result? {
+        Flow::Normal(value) | Flow::Return(value) => Ok(value),
+        Flow::Exit(status) => {
+            ctx.pending_exit = Some(status);
+            Ok(Value::Void)
         }
-        _ => {}
+        Flow::Break(label) | Flow::Continue(label) => Err(stray_jump(label)),
     }
 }
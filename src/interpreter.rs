@@ -0,0 +1,370 @@
+use crate::{
+    Environment, LingConfig, LingError, LingParser, LingResult, Located, Rule, Value,
+    evaluator::evaluate_expression,
+    executor::{
+        Effect, ExecContext, Flow, MemoCache, ValueFormatter, execute, is_expression_statement,
+        stray_jump,
+    },
+    parser,
+};
+use pest::error::LineColLocation;
+use pest::iterators::Pair;
+use pest::{Parser, Position};
+use std::collections::BTreeMap;
+
+/// Stateful interpreter that owns an environment and can execute a loaded
+/// program one top-level statement at a time (e.g. for a step-debugger)
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
+    env: Environment,
+    config: LingConfig,
+    // The loaded program, for turning offsets into lines and columns
+    source: String,
+    // Source text of each top-level statement and where it starts in the
+    // program, re-parsed when stepped
+    statements: Vec<(usize, String)>,
+    // Whether each statement of the loaded program outside a function has run
+    coverage: BTreeMap<parser::Span, bool>,
+    cursor: usize,
+    // Captured print output, bounded by `LingConfig::max_output_bytes`
+    output: Vec<u8>,
+    // Captured 错误输出 diagnostics, kept apart from `output`
+    error_output: Vec<u8>,
+    // Status passed to an exit statement, once one has run
+    exit_code: Option<i64>,
+    // Results of memoized function calls, kept across steps and runs
+    memo: MemoCache,
+    // How print renders values, when the host overrides `Display`
+    value_formatter: Option<ValueFormatter>,
+    // Expressions kept up to date with the variables they read
+    reactives: Vec<Reactive>,
+    // Line and column where the last failed load or step went wrong
+    error_location: Option<(usize, usize)>,
+}
+
+/// A variable defined by an expression rather than a value
+#[derive(Debug, Clone)]
+struct Reactive {
+    name: String,
+    source: String,
+    inputs: Vec<String>,
+}
+
+impl Interpreter {
+    /// Create an interpreter with the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an interpreter with a custom configuration
+    pub fn with_config(config: LingConfig) -> Self {
+        Interpreter {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Parse a program and queue its top-level statements for stepping
+    pub fn load(&mut self, source: &str) -> LingResult<()> {
+        self.error_location = None;
+        parser::check_nesting(source, self.config.max_parse_depth)?;
+        let source = parser::canonicalize_glyphs(source);
+        let pairs = LingParser::parse(Rule::PROGRAM, &source).map_err(|e| {
+            let (LineColLocation::Pos(start) | LineColLocation::Span(start, _)) = e.line_col;
+            self.error_location = Some(start);
+            LingError::ParseError(e.to_string())
+        })?;
+
+        self.statements = Vec::new();
+        self.coverage = BTreeMap::new();
+        for pair in pairs.flat_map(|pair| pair.into_inner()) {
+            if pair.as_rule() == Rule::STATEMENT {
+                let start = pair.as_span().start();
+                self.statements.push((start, pair.as_str().to_string()));
+                collect_statement_spans(pair, &mut self.coverage);
+            }
+        }
+        self.cursor = 0;
+        self.exit_code = None;
+        self.source = source;
+
+        Ok(())
+    }
+
+    /// Execute exactly one top-level statement, returning its value, or
+    /// `None` once the loaded program is exhausted. An exit statement returns
+    /// its status as a `Value::Number` and skips the rest of the program
+    pub fn step(&mut self) -> LingResult<Option<Value>> {
+        Ok(self.step_statement()?.map(|(value, _)| value))
+    }
+
+    /// `step`, also telling whether the value is one `run` reports: that of
+    /// an expression statement or of an exit
+    fn step_statement(&mut self) -> LingResult<Option<(Value, bool)>> {
+        let Some((offset, source)) = self.statements.get(self.cursor) else {
+            return Ok(None);
+        };
+        // Advance first so a failing statement is not retried forever
+        self.cursor += 1;
+        self.error_location = None;
+
+        let mut pairs = LingParser::parse(Rule::STATEMENT, source)
+            .map_err(|e| LingError::ParseError(e.to_string()))?;
+        let statement = pairs
+            .next()
+            .ok_or_else(|| LingError::ParseError("Empty statement".to_string()))?;
+        let is_expression = is_expression_statement(&statement);
+
+        let written = self.output.len();
+        let mut ctx = ExecContext::new(&mut self.output, self.config.clone())
+            .with_output_bytes(written)
+            .with_memo(std::mem::take(&mut self.memo))
+            .with_formatter(self.value_formatter.clone())
+            .with_err_out(&mut self.error_output)
+            .track_coverage();
+        let flow = execute(statement, &mut self.env, &mut ctx);
+        for span in ctx.take_coverage() {
+            let span = parser::Span {
+                start: span.start + offset,
+                end: span.end + offset,
+            };
+            if let Some(covered) = self.coverage.get_mut(&span) {
+                *covered = true;
+            }
+        }
+        if flow.is_err() {
+            let at = offset + ctx.error_at().unwrap_or(0);
+            self.error_location = Position::new(&self.source, at).map(|pos| pos.line_col());
+        }
+        self.memo = ctx.into_memo();
+        self.react()?;
+        match flow? {
+            Flow::Normal(value) | Flow::Return(value) => Ok(Some((value, is_expression))),
+            Flow::Exit(code) => {
+                // Skip whatever is left of the program
+                self.exit_code = Some(code);
+                self.cursor = self.statements.len();
+                Ok(Some((Value::Number(code), true)))
+            }
+            Flow::Break(label) | Flow::Continue(label) => Err(stray_jump(label)),
+        }
+    }
+
+    /// Load and run a whole program against the retained environment,
+    /// returning the value of its last expression statement (declarations
+    /// and prints don't count), or the exit status if an exit statement
+    /// ended it
+    pub fn run(&mut self, source: &str) -> LingResult<Value> {
+        self.load(source)?;
+        let mut last = Value::Void;
+        while let Some((value, reported)) = self.step_statement()? {
+            if reported {
+                last = value;
+            }
+        }
+        Ok(last)
+    }
+
+    /// Run one statement or expression against the retained environment,
+    /// e.g. a line typed at a REPL, returning its value
+    pub fn feed(&mut self, line: &str) -> LingResult<Value> {
+        self.load(line)?;
+        if self.statements.len() > 1 {
+            let found = self.statements.len();
+            self.statements.clear();
+            return Err(LingError::ParseError(format!(
+                "Expected a single statement, found {}",
+                found
+            )));
+        }
+        Ok(self.step()?.unwrap_or(Value::Void))
+    }
+
+    /// Run a program against a scratch copy of the environment, returning the
+    /// variable writes and prints it would perform. Neither the environment
+    /// nor the captured output is changed
+    pub fn dry_run(&self, source: &str) -> LingResult<Vec<Effect>> {
+        parser::check_nesting(source, self.config.max_parse_depth)?;
+        let source = parser::canonicalize_glyphs(source);
+        let program = LingParser::parse(Rule::PROGRAM, &source)
+            .map_err(|e| LingError::ParseError(e.to_string()))?
+            .next()
+            .ok_or_else(|| LingError::ParseError("Empty program".to_string()))?;
+
+        let mut env = self.env.clone();
+        let mut sink = std::io::sink();
+        let mut ctx = ExecContext::new(&mut sink, self.config.clone())
+            .with_memo(self.memo.clone())
+            .with_formatter(self.value_formatter.clone())
+            .dry_run();
+        if let Flow::Break(label) | Flow::Continue(label) = execute(program, &mut env, &mut ctx)? {
+            return Err(stray_jump(label));
+        }
+        Ok(ctx.take_effects())
+    }
+
+    /// Bind `name` to the value of the expression `source`, and re-evaluate
+    /// it whenever a variable it reads is set or updated, including another
+    /// reactive. The expression is evaluated once right away, so what it reads
+    /// must already be defined; a reactive closing a cycle is rejected
+    pub fn define_reactive(&mut self, name: &str, source: &str) -> LingResult<()> {
+        parser::check_nesting(source, self.config.max_parse_depth)?;
+        let source = parser::canonicalize_glyphs(source);
+        let inputs = parser::expression_variables(parse_expression_source(&source)?);
+        let value = self.evaluate(&source)?;
+        for input in &inputs {
+            self.env.watch(input);
+        }
+        self.reactives.push(Reactive {
+            name: name.to_string(),
+            source,
+            inputs,
+        });
+        self.env.set(name.to_string(), value);
+        self.react().inspect_err(|_| {
+            self.reactives.pop();
+        })
+    }
+
+    /// Re-evaluate the reactives whose inputs were written, until no more
+    /// change. Each round settles one link of a chain of reactives, so
+    /// needing more rounds than there are reactives means a cycle
+    fn react(&mut self) -> LingResult<()> {
+        for _ in 0..=self.reactives.len() {
+            let written = self.env.take_writes();
+            if written.is_empty() {
+                return Ok(());
+            }
+            for reactive in self.reactives.clone() {
+                if reactive.inputs.iter().any(|input| written.contains(input)) {
+                    let value = self.evaluate(&reactive.source)?;
+                    self.env.set(reactive.name, value);
+                }
+            }
+        }
+        Err(LingError::RuntimeError(
+            "Reactive values depend on each other in a cycle".to_string(),
+        ))
+    }
+
+    fn evaluate(&mut self, source: &str) -> LingResult<Value> {
+        let expression = parse_expression_source(source)?;
+        let written = self.output.len();
+        let mut ctx = ExecContext::new(&mut self.output, self.config.clone())
+            .with_output_bytes(written)
+            .with_memo(std::mem::take(&mut self.memo))
+            .with_formatter(self.value_formatter.clone())
+            .with_err_out(&mut self.error_output);
+        let value = evaluate_expression(expression, &mut self.env, &mut ctx);
+        self.memo = ctx.into_memo();
+        value
+    }
+
+    /// Render printed values with `format` instead of their `Display` form
+    pub fn set_value_formatter(&mut self, format: impl Fn(&Value) -> String + 'static) {
+        self.value_formatter = Some(ValueFormatter::new(format));
+    }
+
+    /// Forget all variables, loaded statements, output and memoized results,
+    /// keeping the config and value formatter
+    pub fn reset(&mut self) {
+        *self = Interpreter {
+            value_formatter: self.value_formatter.take(),
+            ..Self::with_config(self.config.clone())
+        };
+    }
+
+    /// Line and column (both 1-based) where the last failed `load` or `step`
+    /// went wrong: the innermost failing expression or statement, or for a
+    /// failure inside a function, the call
+    pub fn error_location(&self) -> Option<(usize, usize)> {
+        self.error_location
+    }
+
+    /// Attach `error_location` to an error this interpreter returned
+    pub fn locate(&self, error: LingError) -> Located<LingError> {
+        Located {
+            error,
+            span: self.error_location,
+        }
+    }
+
+    /// Check whether every loaded statement has been executed
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.statements.len()
+    }
+
+    /// Each statement of the loaded program with whether it has run yet, in
+    /// source order. Statements inside function bodies are not tracked
+    pub fn coverage(&self) -> Vec<(parser::Span, bool)> {
+        self.coverage
+            .iter()
+            .map(|(span, covered)| (*span, *covered))
+            .collect()
+    }
+
+    /// Index of the next statement to be executed
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Get the interpreter's environment for inspection between steps
+    pub fn env(&self) -> &Environment {
+        &self.env
+    }
+
+    /// Status of the exit statement that ended the program, if any
+    pub fn exit_code(&self) -> Option<i64> {
+        self.exit_code
+    }
+
+    /// Get everything printed so far
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+
+    /// Get everything printed to the diagnostics stream so far
+    pub fn error_output(&self) -> String {
+        String::from_utf8_lossy(&self.error_output).into_owned()
+    }
+
+    /// Get the interpreter's configuration
+    pub fn config(&self) -> &LingConfig {
+        &self.config
+    }
+}
+
+/// Note every statement under `pair` as not yet run, skipping function
+/// bodies
+fn collect_statement_spans(pair: Pair<Rule>, coverage: &mut BTreeMap<parser::Span, bool>) {
+    match pair.as_rule() {
+        Rule::FUNC_DEF | Rule::LAMBDA => return,
+        Rule::STATEMENT => {
+            let span = pair.as_span();
+            let span = parser::Span {
+                start: span.start(),
+                end: span.end(),
+            };
+            coverage.insert(span, false);
+        }
+        _ => {}
+    }
+    for inner in pair.into_inner() {
+        collect_statement_spans(inner, coverage);
+    }
+}
+
+/// Parse source that must be exactly one expression
+fn parse_expression_source(source: &str) -> LingResult<Pair<'_, Rule>> {
+    let expression = LingParser::parse(Rule::EXPRESSION, source.trim())
+        .map_err(|e| LingError::ParseError(e.to_string()))?
+        .next()
+        .ok_or_else(|| LingError::ParseError("Empty expression".to_string()))?;
+    if expression.as_str().len() != source.trim().len() {
+        return Err(LingError::ParseError(format!(
+            "Expected a single expression, found '{}'",
+            source.trim()
+        )));
+    }
+    Ok(expression)
+}
@@ -1,14 +1,17 @@
 use pest_derive::Parser;
 
+pub mod builtins;
 pub mod codegen;
 pub mod evaluator;
 pub mod executor;
+pub mod interpreter;
 pub mod ling_number;
 pub mod parser;
 
 // Re-export commonly used types
 pub use environment::Environment;
-pub use error::{LingError, LingResult};
+pub use error::{ErrorCategory, LingError, LingResult, Located};
+pub use interpreter::Interpreter;
 pub use value::Value;
 
 /// Main parser for the alien/ling language
@@ -19,26 +22,246 @@ pub struct LingParser;
 // Deprecated alias for backward compatibility
 pub type ChineseLangParser = LingParser;
 
+/// Run a snippet in a fresh interpreter and return the value of its last
+/// expression statement, or `Value::Void` if it has none; printed output is
+/// captured and discarded
+pub fn eval(source: &str) -> LingResult<Value> {
+    Interpreter::new().run(source)
+}
+
 /// Module for value types
 pub mod value {
+    use pest::Parser;
+    use pest::iterators::Pairs;
+    use std::cell::RefCell;
+    use std::collections::{BTreeMap, HashMap};
     use std::fmt;
+    use std::hash::{Hash, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use crate::{LingError, LingParser, LingResult, Rule};
 
     /// Runtime value types in the language
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug)]
     pub enum Value {
         Number(i64),
+        Float(f64),
         String(String),
         Boolean(bool),
+        Char(char),
+        Array(Vec<Value>),
+        Map(BTreeMap<String, Value>),
+        /// Inclusive integer range stepping upwards by `step` (always positive)
+        Range {
+            start: i64,
+            end: i64,
+            step: i64,
+        },
         Function(FunctionValue),
         Void,
     }
 
     /// Function value representation
-    #[derive(Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug)]
     pub struct FunctionValue {
-        pub name: String,
+        pub name: String, // Empty for lambdas
         pub params: Vec<String>,
-        pub body: String, // Store as AST later
+        pub body: FunctionBody,
+        pub captured: Vec<(String, Value)>, // Closure environment
+        pub memoized: bool,                 // Cache results by argument values
+        pub bound: Vec<Value>,              // Leading arguments fixed by 部分应用
+        id: u64,                            // Identity, shared only by clones
+    }
+
+    /// The statements of a function body, parsed once when the function is
+    /// defined so calls walk them without re-parsing
+    #[derive(Clone)]
+    pub struct FunctionBody {
+        source: &'static str,
+        statements: Pairs<'static, Rule>,
+    }
+
+    thread_local! {
+        // Parsed bodies by source text. Parse trees borrow their text, so each
+        // distinct body is kept for the life of the thread and shared by every
+        // later definition of it (e.g. a lambda evaluated in a loop)
+        static PARSED_BODIES: RefCell<HashMap<String, FunctionBody>> =
+            RefCell::new(HashMap::new());
+    }
+
+    impl FunctionBody {
+        /// Parse `source` as a sequence of statements
+        pub fn parse(source: &str) -> LingResult<Self> {
+            if let Some(body) = PARSED_BODIES.with(|bodies| bodies.borrow().get(source).cloned()) {
+                return Ok(body);
+            }
+            let text: &'static str = Box::leak(source.into());
+            let statements = LingParser::parse(Rule::PROGRAM, text)
+                .map_err(|e| LingError::ParseError(e.to_string()))?;
+            let body = FunctionBody {
+                source: text,
+                statements,
+            };
+            PARSED_BODIES
+                .with(|bodies| bodies.borrow_mut().insert(source.to_string(), body.clone()));
+            Ok(body)
+        }
+
+        /// The body's source text
+        pub fn source(&self) -> &str {
+            self.source
+        }
+
+        /// The parsed statements, ready to execute
+        pub fn statements(&self) -> Pairs<'static, Rule> {
+            self.statements.clone()
+        }
+    }
+
+    impl fmt::Debug for FunctionBody {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_tuple("FunctionBody").field(&self.source).finish()
+        }
+    }
+
+    static NEXT_FUNCTION_ID: AtomicU64 = AtomicU64::new(0);
+
+    impl FunctionValue {
+        /// Create a function with a fresh identity
+        pub fn new(
+            name: String,
+            params: Vec<String>,
+            body: FunctionBody,
+            captured: Vec<(String, Value)>,
+        ) -> Self {
+            FunctionValue {
+                name,
+                params,
+                body,
+                captured,
+                memoized: false,
+                bound: Vec::new(),
+                id: NEXT_FUNCTION_ID.fetch_add(1, Ordering::Relaxed),
+            }
+        }
+
+        /// Number of arguments a call still has to pass
+        pub fn arity(&self) -> usize {
+            self.params.len() - self.bound.len()
+        }
+
+        /// Tag string standing in for the function in JSON, which has no
+        /// function values, e.g. `<function 加(a, b)>`
+        pub fn json_tag(&self) -> String {
+            format!(
+                "<function {}({})>",
+                self.name,
+                self.params[self.bound.len()..].join(", ")
+            )
+        }
+
+        /// A new function taking the remaining arguments, with `args` bound
+        /// ahead of them
+        pub fn bind(&self, args: &[Value]) -> crate::LingResult<FunctionValue> {
+            if args.len() > self.arity() {
+                return Err(crate::LingError::ArgumentMismatch {
+                    expected: self.arity(),
+                    found: args.len(),
+                });
+            }
+            let mut bound = self.bound.clone();
+            bound.extend_from_slice(args);
+            Ok(FunctionValue {
+                bound,
+                id: NEXT_FUNCTION_ID.fetch_add(1, Ordering::Relaxed),
+                ..self.clone()
+            })
+        }
+    }
+
+    /// Functions are equal only to themselves (or their clones), never by structure
+    impl PartialEq for FunctionValue {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for FunctionValue {}
+
+    /// Hashes the identity, consistent with equality
+    impl Hash for FunctionValue {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.id.hash(state);
+        }
+    }
+
+    /// Structural equality. Floats compare by bit pattern, so NaN equals itself
+    /// and equality agrees with `Hash`; the language's `==` compares them
+    /// numerically instead
+    impl PartialEq for Value {
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (Value::Number(a), Value::Number(b)) => a == b,
+                (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+                (Value::String(a), Value::String(b)) => a == b,
+                (Value::Boolean(a), Value::Boolean(b)) => a == b,
+                (Value::Char(a), Value::Char(b)) => a == b,
+                (Value::Array(a), Value::Array(b)) => a == b,
+                (Value::Map(a), Value::Map(b)) => a == b,
+                (
+                    Value::Range { start, end, step },
+                    Value::Range {
+                        start: s,
+                        end: e,
+                        step: st,
+                    },
+                ) => (start, end, step) == (s, e, st),
+                (Value::Function(a), Value::Function(b)) => a == b,
+                (Value::Void, Value::Void) => true,
+                _ => false,
+            }
+        }
+    }
+
+    impl Eq for Value {}
+
+    impl Hash for Value {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            std::mem::discriminant(self).hash(state);
+            match self {
+                Value::Number(n) => n.hash(state),
+                Value::Float(f) => f.to_bits().hash(state),
+                Value::String(s) => s.hash(state),
+                Value::Boolean(b) => b.hash(state),
+                Value::Char(c) => c.hash(state),
+                Value::Array(items) => items.hash(state),
+                Value::Map(entries) => entries.hash(state),
+                Value::Range { start, end, step } => (start, end, step).hash(state),
+                Value::Function(func) => func.hash(state),
+                Value::Void => {}
+            }
+        }
+    }
+
+    /// Orders numbers (integer or float), strings, chars and booleans among
+    /// their own kind, and arrays element by element; any other pair is
+    /// unordered unless equal
+    impl PartialOrd for Value {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            match (self, other) {
+                (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+                (Value::Float(_), Value::Number(_) | Value::Float(_))
+                | (Value::Number(_), Value::Float(_)) => {
+                    self.to_float()?.partial_cmp(&other.to_float()?)
+                }
+                (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                (Value::Char(a), Value::Char(b)) => a.partial_cmp(b),
+                (Value::Boolean(a), Value::Boolean(b)) => a.partial_cmp(b),
+                (Value::Array(a), Value::Array(b)) => a.partial_cmp(b),
+                (a, b) if a == b => Some(std::cmp::Ordering::Equal),
+                _ => None,
+            }
+        }
     }
 
     impl Value {
@@ -47,44 +270,325 @@ pub mod value {
             match self {
                 Value::Boolean(b) => *b,
                 Value::Number(n) => *n != 0,
+                Value::Float(f) => *f != 0.0 && !f.is_nan(),
                 Value::String(s) => !s.is_empty(),
                 Value::Void => false,
-                Value::Function(_) => true,
+                // Collections are truthy when non-empty, like strings
+                Value::Array(items) => !items.is_empty(),
+                Value::Map(entries) => !entries.is_empty(),
+                Value::Range { start, end, .. } => start <= end,
+                Value::Char(_) | Value::Function(_) => true,
+            }
+        }
+
+        /// Check that a value can key a memoization cache: functions (also
+        /// nested in arrays or maps) have no stable value to hash
+        pub fn ensure_hashable(&self) -> crate::LingResult<()> {
+            match self {
+                Value::Function(_) => Err(crate::LingError::TypeError {
+                    expected: "hashable value".to_string(),
+                    found: "function".to_string(),
+                }),
+                Value::Array(items) => items.iter().try_for_each(Value::ensure_hashable),
+                Value::Map(entries) => entries.values().try_for_each(Value::ensure_hashable),
+                _ => Ok(()),
+            }
+        }
+
+        /// Iterate the elements of an array or range in order. Ranges are
+        /// walked lazily rather than expanded up front
+        pub fn elements(&self) -> Option<Box<dyn Iterator<Item = Value> + '_>> {
+            match self {
+                Value::Array(items) => Some(Box::new(items.iter().cloned())),
+                Value::Range { start, end, step } => Some(Box::new(
+                    (*start..=*end)
+                        .step_by((*step).max(1) as usize)
+                        .map(Value::Number),
+                )),
+                _ => None,
+            }
+        }
+
+        /// Number of elements in an array or range, without expanding the range
+        pub fn element_count(&self) -> Option<usize> {
+            match self {
+                Value::Array(items) => Some(items.len()),
+                Value::Range { start, end, step } if start <= end => {
+                    let span = (*end as i128 - *start as i128) as u128;
+                    let count = span / (*step).max(1) as u128 + 1;
+                    Some(count.try_into().unwrap_or(usize::MAX))
+                }
+                Value::Range { .. } => Some(0),
+                _ => None,
             }
         }
 
         /// Convert value to number if possible
         pub fn to_number(&self) -> Option<i64> {
+            self.coerce_to_number(true).ok()
+        }
+
+        /// Convert value to a float if possible
+        pub fn to_float(&self) -> Option<f64> {
             match self {
-                Value::Number(n) => Some(*n),
+                Value::Number(n) => Some(*n as f64),
+                Value::Float(f) => Some(*f),
                 Value::String(s) => s.parse().ok(),
-                Value::Boolean(b) => Some(if *b { 1 } else { 0 }),
+                Value::Boolean(b) => Some(if *b { 1.0 } else { 0.0 }),
                 _ => None,
             }
         }
 
+        /// Coerce a value to a number for arithmetic and comparison.
+        /// Numbers pass through, finite floats are truncated, booleans become
+        /// 1 or 0 and numeric strings are parsed. Leniently, void and
+        /// non-numeric strings count as 0; strictly they are type errors.
+        /// Chars, arrays, maps, ranges, functions and NaN or infinite floats
+        /// never coerce.
+        pub fn coerce_to_number(&self, strict: bool) -> crate::LingResult<i64> {
+            let type_error = || crate::LingError::TypeError {
+                expected: "number".to_string(),
+                found: self.type_name().to_string(),
+            };
+            match self {
+                Value::Number(n) => Ok(*n),
+                Value::Float(f) if f.is_finite() => Ok(*f as i64),
+                Value::Boolean(b) => Ok(if *b { 1 } else { 0 }),
+                Value::String(s) => match s.parse() {
+                    Ok(n) => Ok(n),
+                    Err(_) if !strict => Ok(0),
+                    Err(_) => Err(type_error()),
+                },
+                Value::Void if !strict => Ok(0),
+                _ => Err(type_error()),
+            }
+        }
+
         /// Get the type name of the value
         pub fn type_name(&self) -> &'static str {
             match self {
                 Value::Number(_) => "number",
+                Value::Float(_) => "float",
                 Value::String(_) => "string",
                 Value::Boolean(_) => "boolean",
+                Value::Char(_) => "char",
+                Value::Array(_) => "array",
+                Value::Map(_) => "map",
+                Value::Range { .. } => "range",
                 Value::Function(_) => "function",
                 Value::Void => "void",
             }
         }
+
+        /// Terminal cells the displayed value takes: two per CJK or other
+        /// East Asian wide character, one per narrow character
+        pub fn display_width(&self) -> usize {
+            self.to_string().chars().map(char_width).sum()
+        }
+
+        /// The sign of a number as -1, 0 or 1
+        pub fn signum(&self) -> crate::LingResult<Value> {
+            match self {
+                Value::Number(n) => Ok(Value::Number(n.signum())),
+                other => Err(crate::LingError::TypeError {
+                    expected: "number".to_string(),
+                    found: other.type_name().to_string(),
+                }),
+            }
+        }
+
+        /// Compare with `other` by the value ordering: -1, 0 or 1, or
+        /// `InvalidOperation` when the two have no order
+        pub fn cmp_value(&self, other: &Value) -> crate::LingResult<i64> {
+            match self.partial_cmp(other) {
+                Some(order) => Ok(order as i64),
+                None => Err(crate::LingError::InvalidOperation {
+                    op: "compare".to_string(),
+                    left: self.type_name().to_string(),
+                    right: other.type_name().to_string(),
+                }),
+            }
+        }
     }
 
-    impl fmt::Display for Value {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Display width of one character, from a small table of the common
+    /// East Asian wide ranges; control characters and combining marks take none
+    fn char_width(c: char) -> usize {
+        const WIDE: &[(u32, u32)] = &[
+            (0x1100, 0x115F),   // Hangul Jamo
+            (0x2E80, 0x303E),   // CJK radicals, symbols and punctuation
+            (0x3041, 0x33FF),   // Kana, Bopomofo, CJK compatibility
+            (0x3400, 0x4DBF),   // CJK extension A
+            (0x4E00, 0x9FFF),   // CJK unified ideographs
+            (0xA000, 0xA4CF),   // Yi
+            (0xAC00, 0xD7A3),   // Hangul syllables
+            (0xF900, 0xFAFF),   // CJK compatibility ideographs
+            (0xFE30, 0xFE4F),   // CJK compatibility forms
+            (0xFF00, 0xFF60),   // Fullwidth forms
+            (0xFFE0, 0xFFE6),   // Fullwidth signs
+            (0x1F300, 0x1F64F), // Pictographs and emoticons
+            (0x1F900, 0x1F9FF), // Supplemental pictographs
+            (0x20000, 0x3FFFD), // CJK extensions B and beyond
+        ];
+        let code = c as u32;
+        if c.is_control() || (0x0300..=0x036F).contains(&code) || code == 0x200B {
+            return 0;
+        }
+        if WIDE.iter().any(|&(lo, hi)| (lo..=hi).contains(&code)) {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// How many levels of nested arrays and maps `Display` shows before
+    /// eliding the rest as `[...]` / `{...}`
+    pub const DISPLAY_DEPTH: usize = 32;
+
+    impl Value {
+        /// Render like `Display`, showing at most `max_depth` levels of nested
+        /// arrays and maps
+        pub fn to_string_depth(&self, max_depth: usize) -> String {
+            let mut out = String::new();
+            // Writing into a String cannot fail
+            let _ = self.write_depth(&mut out, max_depth);
+            out
+        }
+
+        fn write_depth(&self, f: &mut dyn fmt::Write, depth: usize) -> fmt::Result {
             match self {
                 Value::Number(n) => write!(f, "{}", n),
+                Value::Float(x) => write!(f, "{}", format_float(*x)),
                 Value::String(s) => write!(f, "{}", s),
                 Value::Boolean(b) => write!(f, "{}", b),
+                Value::Char(c) => write!(f, "{}", c),
+                Value::Array(_) if depth == 0 => write!(f, "[...]"),
+                Value::Map(_) if depth == 0 => write!(f, "{{...}}"),
+                Value::Array(items) => {
+                    write!(f, "[")?;
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        item.write_depth(f, depth - 1)?;
+                    }
+                    write!(f, "]")
+                }
+                Value::Map(entries) => {
+                    write!(f, "{{")?;
+                    for (i, (key, value)) in entries.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}: ", key)?;
+                        value.write_depth(f, depth - 1)?;
+                    }
+                    write!(f, "}}")
+                }
+                Value::Range { start, end, step } => {
+                    write!(f, "{}…{}", start, end)?;
+                    if *step != 1 {
+                        write!(f, " 步 {}", step)?;
+                    }
+                    Ok(())
+                }
                 Value::Function(func) => write!(f, "<function {}>", func.name),
                 Value::Void => write!(f, ""),
             }
         }
+
+        /// Render as JSON with each nested array or map entry on its own line,
+        /// indented by `indent` spaces per level. Ranges become arrays, void
+        /// becomes null and functions their signature string
+        pub fn to_json_pretty(&self, indent: usize) -> String {
+            let mut out = String::new();
+            self.write_json(&mut out, indent, 0);
+            out
+        }
+
+        fn write_json(&self, out: &mut String, indent: usize, level: usize) {
+            let newline = |out: &mut String, level: usize| {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent * level));
+            };
+            match self {
+                Value::Number(n) => out.push_str(&n.to_string()),
+                // JSON has no NaN or infinity
+                Value::Float(x) if x.is_finite() => out.push_str(&format_float(*x)),
+                Value::Float(_) => out.push_str("null"),
+                Value::Boolean(b) => out.push_str(&b.to_string()),
+                Value::String(s) => write_json_string(out, s),
+                Value::Char(c) => write_json_string(out, &c.to_string()),
+                Value::Void => out.push_str("null"),
+                Value::Function(func) => write_json_string(out, &func.json_tag()),
+                Value::Range { .. } => {
+                    Value::Array(self.elements().unwrap().collect()).write_json(out, indent, level)
+                }
+                Value::Array(items) if items.is_empty() => out.push_str("[]"),
+                Value::Map(entries) if entries.is_empty() => out.push_str("{}"),
+                Value::Array(items) => {
+                    out.push('[');
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        newline(out, level + 1);
+                        item.write_json(out, indent, level + 1);
+                    }
+                    newline(out, level);
+                    out.push(']');
+                }
+                Value::Map(entries) => {
+                    out.push('{');
+                    for (i, (key, value)) in entries.iter().enumerate() {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        newline(out, level + 1);
+                        write_json_string(out, key);
+                        out.push_str(": ");
+                        value.write_json(out, indent, level + 1);
+                    }
+                    newline(out, level);
+                    out.push('}');
+                }
+            }
+        }
+    }
+
+    /// Show a float in the shortest form that reads back as the same value,
+    /// keeping a decimal point so it still reads as a float (2.5, 3 → 3.0,
+    /// 1e-11 → 0.00000000001)
+    fn format_float(x: f64) -> String {
+        let shortest = x.to_string();
+        if !x.is_finite() || shortest.contains('.') {
+            shortest
+        } else {
+            format!("{}.0", shortest)
+        }
+    }
+
+    /// Quote a string for JSON, escaping quotes, backslashes and control characters
+    fn write_json_string(out: &mut String, s: &str) {
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+    }
+
+    impl fmt::Display for Value {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.write_depth(f, DISPLAY_DEPTH)
+        }
     }
 
     impl From<i64> for Value {
@@ -93,6 +597,12 @@ pub mod value {
         }
     }
 
+    impl From<f64> for Value {
+        fn from(x: f64) -> Self {
+            Value::Float(x)
+        }
+    }
+
     impl From<String> for Value {
         fn from(s: String) -> Self {
             Value::String(s)
@@ -110,23 +620,105 @@ pub mod value {
             Value::Boolean(b)
         }
     }
+
+    impl From<Vec<Value>> for Value {
+        fn from(items: Vec<Value>) -> Self {
+            Value::Array(items)
+        }
+    }
+
+    impl From<BTreeMap<String, Value>> for Value {
+        fn from(entries: BTreeMap<String, Value>) -> Self {
+            Value::Map(entries)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl From<Value> for serde_json::Value {
+        fn from(value: Value) -> Self {
+            match value {
+                Value::Number(n) => serde_json::Value::from(n),
+                // NaN and infinities have no JSON form
+                Value::Float(x) => serde_json::Number::from_f64(x)
+                    .map_or(serde_json::Value::Null, serde_json::Value::Number),
+                Value::String(s) => serde_json::Value::String(s),
+                Value::Boolean(b) => serde_json::Value::Bool(b),
+                Value::Char(c) => serde_json::Value::String(c.to_string()),
+                Value::Array(items) => {
+                    serde_json::Value::Array(items.into_iter().map(Into::into).collect())
+                }
+                Value::Map(entries) => serde_json::Value::Object(
+                    entries.into_iter().map(|(k, v)| (k, v.into())).collect(),
+                ),
+                Value::Range { .. } => {
+                    serde_json::Value::Array(value.elements().unwrap().map(Into::into).collect())
+                }
+                // Functions have no JSON form, so they travel as a tag string
+                Value::Function(func) => serde_json::Value::String(func.json_tag()),
+                Value::Void => serde_json::Value::Null,
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl Value {
+        /// Convert a host JSON value into a runtime value
+        pub fn from_json_value(json: serde_json::Value) -> crate::LingResult<Value> {
+            let type_error = |expected: &str, found: &str| crate::LingError::TypeError {
+                expected: expected.to_string(),
+                found: found.to_string(),
+            };
+            match json {
+                serde_json::Value::Null => Ok(Value::Void),
+                serde_json::Value::Bool(b) => Ok(Value::Boolean(b)),
+                serde_json::Value::Number(n) => n
+                    .as_i64()
+                    .map(Value::Number)
+                    .or_else(|| n.as_f64().map(Value::Float))
+                    .ok_or_else(|| type_error("number", "out-of-range number")),
+                serde_json::Value::String(s) => Ok(Value::String(s)),
+                serde_json::Value::Array(items) => items
+                    .into_iter()
+                    .map(Value::from_json_value)
+                    .collect::<crate::LingResult<Vec<_>>>()
+                    .map(Value::Array),
+                serde_json::Value::Object(entries) => entries
+                    .into_iter()
+                    .map(|(k, v)| Value::from_json_value(v).map(|v| (k, v)))
+                    .collect::<crate::LingResult<BTreeMap<_, _>>>()
+                    .map(Value::Map),
+            }
+        }
+    }
 }
 
 /// Module for environment/scope management
 pub mod environment {
     use super::value::Value;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap, HashSet};
 
     /// Runtime environment to store variables and scopes
     #[derive(Clone, Debug)]
     pub struct Environment {
         scopes: Vec<Scope>,
+        // Names whose writes are logged for the host to react to
+        watched: HashSet<String>,
+        writes: Vec<String>,
     }
 
     /// A single scope containing variables
     #[derive(Clone, Debug)]
     struct Scope {
         variables: HashMap<String, Variable>,
+        is_function_boundary: bool, // Lookups stop here and fall through to globals
+    }
+
+    /// A change to a single variable between two environment snapshots
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum VarChange {
+        Added(String, Value),
+        Removed(String, Value),
+        Changed(String, Value, Value), // name, old, new
     }
 
     /// Variable metadata
@@ -142,6 +734,8 @@ pub mod environment {
         pub fn new() -> Self {
             Environment {
                 scopes: vec![Scope::new()],
+                watched: HashSet::new(),
+                writes: Vec::new(),
             }
         }
 
@@ -150,6 +744,17 @@ pub mod environment {
             self.scopes.push(Scope::new());
         }
 
+        /// Push a function scope seeded with its captured closure; lookups
+        /// from inside skip the caller's scopes and fall through to globals
+        pub fn push_function_scope(&mut self, captured: Vec<(String, Value)>) {
+            let mut scope = Scope::new();
+            scope.is_function_boundary = true;
+            self.scopes.push(scope);
+            for (name, value) in captured {
+                self.set(name, value);
+            }
+        }
+
         /// Pop the current scope
         pub fn pop_scope(&mut self) {
             if self.scopes.len() > 1 {
@@ -162,7 +767,8 @@ pub mod environment {
             self.scopes.len()
         }
 
-        /// Set a variable in the current scope
+        /// Set a variable in the current scope, shadowing any outer binding of
+        /// the name, constant or not
         pub fn set(&mut self, name: String, value: Value) {
             self.set_with_mutability(name, value, true);
         }
@@ -170,6 +776,7 @@ pub mod environment {
         /// Set a variable with mutability flag
         pub fn set_with_mutability(&mut self, name: String, value: Value, is_mutable: bool) {
             let normalized = Self::normalize_identifier(&name);
+            self.log_write(&name);
 
             if let Some(scope) = self.scopes.last_mut() {
                 scope.variables.insert(
@@ -188,60 +795,157 @@ pub mod environment {
             self.set_with_mutability(name, value, false);
         }
 
-        /// Update an existing variable
+        /// Update the innermost binding of an existing variable. A shadowing
+        /// binding is updated on its own; a constant is never changed
         pub fn update(&mut self, name: &str, value: Value) -> Result<(), String> {
-            // Search from innermost to outermost scope
-            for scope in self.scopes.iter_mut().rev() {
-                if let Some(var) = scope.variables.get_mut(name) {
-                    if !var.is_mutable {
-                        return Err(format!("Cannot assign to immutable variable '{}'", name));
-                    }
-                    var.value = value;
-                    return Ok(());
-                }
+            let Some(index) = self.resolve(name) else {
+                return Err(format!("Undefined variable '{}'", name));
+            };
+            let var = self.scopes[index].variables.get_mut(name).unwrap();
+            if !var.is_mutable {
+                return Err(format!("Cannot assign to immutable variable '{}'", name));
+            }
+            var.value = value;
+            self.log_write(name);
+            Ok(())
+        }
+
+        /// Log every later `set` or `update` of `name`, to be collected with
+        /// `take_writes`
+        pub fn watch(&mut self, name: &str) {
+            self.watched.insert(name.to_string());
+        }
+
+        /// Drain the watched names written since the last call, in write order
+        pub fn take_writes(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.writes)
+        }
+
+        fn log_write(&mut self, name: &str) {
+            if self.watched.contains(name) && !self.writes.iter().any(|w| w == name) {
+                self.writes.push(name.to_string());
             }
-            Err(format!("Undefined variable '{}'", name))
         }
 
         /// Get a variable value
         pub fn get(&self, name: &str) -> Option<Value> {
-            // Search from innermost to outermost scope
-            for scope in self.scopes.iter().rev() {
-                if let Some(var) = scope.variables.get(name) {
-                    return Some(var.value.clone());
-                }
-            }
-            None
+            self.lookup(name).map(|var| var.value.clone())
         }
 
         /// Check if a variable exists
         pub fn exists(&self, name: &str) -> bool {
-            self.scopes
-                .iter()
-                .rev()
-                .any(|scope| scope.variables.contains_key(name))
+            self.resolve(name).is_some()
+        }
+
+        /// Check if a name is a constant holding a plain literal (number,
+        /// string or boolean), which tooling can safely inline
+        pub fn is_constant_literal(&self, name: &str) -> bool {
+            self.lookup(name).is_some_and(|var| {
+                !var.is_mutable
+                    && matches!(
+                        var.value,
+                        Value::Number(_) | Value::String(_) | Value::Boolean(_)
+                    )
+            })
         }
 
         /// Check if a variable is mutable
         pub fn is_mutable(&self, name: &str) -> Option<bool> {
-            for scope in self.scopes.iter().rev() {
-                if let Some(var) = scope.variables.get(name) {
-                    return Some(var.is_mutable);
-                }
-            }
-            None
+            self.lookup(name).map(|var| var.is_mutable)
         }
 
         /// Get the normalized (ASCII-safe) name of a variable
         pub fn get_normalized_name(&self, name: &str) -> Option<String> {
-            for scope in self.scopes.iter().rev() {
-                if let Some(var) = scope.variables.get(name) {
-                    return Some(var.normalized_name.clone());
+            self.lookup(name).map(|var| var.normalized_name.clone())
+        }
+
+        /// Find the index of the scope a name resolves to, searching from
+        /// innermost outwards and jumping to globals at a function boundary
+        fn resolve(&self, name: &str) -> Option<usize> {
+            for (index, scope) in self.scopes.iter().enumerate().rev() {
+                if scope.variables.contains_key(name) {
+                    return Some(index);
+                }
+                if scope.is_function_boundary {
+                    return self.scopes[0].variables.contains_key(name).then_some(0);
                 }
             }
             None
         }
 
+        /// Find the variable a name resolves to
+        fn lookup(&self, name: &str) -> Option<&Variable> {
+            self.resolve(name)
+                .and_then(|index| self.scopes[index].variables.get(name))
+        }
+
+        /// Get the variables visible from here that are not globals (the
+        /// locals a closure created now would capture), innermost first
+        pub fn local_bindings(&self) -> Vec<(String, Value)> {
+            let mut seen = std::collections::HashSet::new();
+            let mut bindings = Vec::new();
+            for scope in self.scopes.iter().skip(1).rev() {
+                for (name, var) in &scope.variables {
+                    if seen.insert(name.clone()) {
+                        bindings.push((name.clone(), var.value.clone()));
+                    }
+                }
+                if scope.is_function_boundary {
+                    break;
+                }
+            }
+            bindings
+        }
+
+        /// The local bindings for just the given names, e.g. a body's
+        /// `parser::body_free_variables`, rather than every visible local
+        pub fn capture_free_vars(&self, names: &[String]) -> Vec<(String, Value)> {
+            self.local_bindings()
+                .into_iter()
+                .filter(|(name, _)| names.contains(name))
+                .collect()
+        }
+
+        /// Get every variable visible from the current scope with its value
+        pub fn flatten(&self) -> BTreeMap<String, Value> {
+            self.scopes
+                .iter()
+                .flat_map(|scope| scope.variables.keys())
+                .filter_map(|name| self.get(name).map(|value| (name.clone(), value)))
+                .collect()
+        }
+
+        /// Export the visible variables as an indented JSON object, keyed by
+        /// name (see `Value::to_json_pretty`)
+        #[cfg(feature = "serde")]
+        pub fn to_json_pretty(&self, indent: usize) -> crate::LingResult<String> {
+            Ok(Value::Map(self.flatten()).to_json_pretty(indent))
+        }
+
+        /// List what changed going from this environment to `other`, by name
+        pub fn diff(&self, other: &Environment) -> Vec<VarChange> {
+            let before = self.flatten();
+            let after = other.flatten();
+
+            let mut changes = Vec::new();
+            for (name, old) in &before {
+                match after.get(name) {
+                    None => changes.push(VarChange::Removed(name.clone(), old.clone())),
+                    Some(new) if new != old => {
+                        changes.push(VarChange::Changed(name.clone(), old.clone(), new.clone()))
+                    }
+                    Some(_) => {}
+                }
+            }
+            for (name, new) in after {
+                if !before.contains_key(&name) {
+                    changes.push(VarChange::Added(name, new));
+                }
+            }
+            changes.sort_by(|a, b| change_name(a).cmp(change_name(b)));
+            changes
+        }
+
         /// Get all variables in the current scope
         pub fn current_scope_vars(&self) -> Vec<String> {
             self.scopes
@@ -294,10 +998,19 @@ pub mod environment {
         }
     }
 
+    fn change_name(change: &VarChange) -> &str {
+        match change {
+            VarChange::Added(name, _)
+            | VarChange::Removed(name, _)
+            | VarChange::Changed(name, _, _) => name,
+        }
+    }
+
     impl Scope {
         fn new() -> Self {
             Scope {
                 variables: HashMap::new(),
+                is_function_boundary: false,
             }
         }
     }
@@ -355,6 +1068,7 @@ pub mod error {
         },
         CompilationError(String),
         IOError(String),
+        StackOverflow(usize), // Nesting limit that was exceeded
     }
 
     impl fmt::Display for LingError {
@@ -387,11 +1101,84 @@ pub mod error {
                 }
                 LingError::CompilationError(msg) => write!(f, "Compilation error: {}", msg),
                 LingError::IOError(msg) => write!(f, "IO error: {}", msg),
+                LingError::StackOverflow(limit) => {
+                    write!(f, "Stack overflow: nesting deeper than {} levels", limit)
+                }
             }
         }
     }
 
     impl std::error::Error for LingError {}
+
+    /// An error with the line and column (both 1-based) it arose at, when known
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Located<E> {
+        pub error: E,
+        pub span: Option<(usize, usize)>,
+    }
+
+    impl<E: fmt::Display> fmt::Display for Located<E> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.error)?;
+            if let Some((line, column)) = self.span {
+                write!(f, " at line {}, col {}", line, column)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<E: fmt::Debug + fmt::Display> std::error::Error for Located<E> {}
+
+    impl From<LingError> for Located<LingError> {
+        fn from(error: LingError) -> Self {
+            Located { error, span: None }
+        }
+    }
+
+    /// Broad bucket of an error, for hosts choosing severity or exit codes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum ErrorCategory {
+        Syntax,
+        Runtime,
+        Type,
+        Compile,
+        Io,
+    }
+
+    impl LingError {
+        /// Get the category this error belongs to
+        pub fn category(&self) -> ErrorCategory {
+            match self {
+                LingError::ParseError(_) => ErrorCategory::Syntax,
+                LingError::RuntimeError(_)
+                | LingError::UndefinedVariable(_)
+                | LingError::ImmutableAssignment(_)
+                | LingError::DivisionByZero
+                | LingError::FunctionNotFound(_)
+                | LingError::ArgumentMismatch { .. }
+                | LingError::StackOverflow(_) => ErrorCategory::Runtime,
+                LingError::TypeError { .. } | LingError::InvalidOperation { .. } => {
+                    ErrorCategory::Type
+                }
+                LingError::CompilationError(_) => ErrorCategory::Compile,
+                LingError::IOError(_) => ErrorCategory::Io,
+            }
+        }
+
+        /// Whether this is a sandbox limit being hit (nesting, loop passes,
+        /// recursion depth or output size) rather than a fault in the program
+        pub fn is_resource_limit(&self) -> bool {
+            match self {
+                LingError::StackOverflow(_) => true,
+                LingError::RuntimeError(msg) => {
+                    msg == "output limit exceeded"
+                        || msg.starts_with("Loop exceeded ")
+                        || msg.starts_with("Maximum recursion depth ")
+                }
+                _ => false,
+            }
+        }
+    }
 }
 
 /// Configuration for the language runtime
@@ -401,6 +1188,11 @@ pub struct LingConfig {
     pub strict_mode: bool,
     pub max_recursion_depth: usize,
     pub enable_alien_syntax: bool,
+    pub max_output_bytes: Option<usize>, // Cap on captured print output
+    pub max_parse_depth: usize,          // Cap on nesting when walking parse trees
+    pub fixed_clock_ms: Option<i64>,     // Value for 时间() instead of the system clock
+    pub float_epsilon: f64,              // Tolerance for float equality; 0 compares exactly
+    pub float_division: bool,            // Inexact integer division yields a float
 }
 
 impl Default for LingConfig {
@@ -410,20 +1202,50 @@ impl Default for LingConfig {
             strict_mode: false,
             max_recursion_depth: 1000,
             enable_alien_syntax: true,
+            max_output_bytes: None,
+            max_parse_depth: 512,
+            fixed_clock_ms: None,
+            float_epsilon: 1e-9,
+            float_division: false,
         }
     }
 }
 
 /// Utility functions for the language
 pub mod utils {
-    use super::Value;
+    use super::{LingConfig, Value};
 
-    /// Check if two values are equal
+    /// Check if two values are equal, comparing floats within the default `float_epsilon`
     pub fn values_equal(left: &Value, right: &Value) -> bool {
+        values_equal_within(left, right, LingConfig::default().float_epsilon)
+    }
+
+    /// Check if two values are equal, treating floats within `epsilon` as equal
+    pub fn values_equal_within(left: &Value, right: &Value, epsilon: f64) -> bool {
         match (left, right) {
             (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Float(_), Value::Number(_) | Value::Float(_))
+            | (Value::Number(_), Value::Float(_)) => match (left.to_float(), right.to_float()) {
+                (Some(l), Some(r)) => l == r || (l - r).abs() <= epsilon,
+                _ => false,
+            },
             (Value::String(l), Value::String(r)) => l == r,
             (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::Char(l), Value::Char(r)) => l == r,
+            (Value::Array(l), Value::Array(r)) => {
+                l.len() == r.len()
+                    && l.iter()
+                        .zip(r)
+                        .all(|(a, b)| values_equal_within(a, b, epsilon))
+            }
+            (Value::Map(l), Value::Map(r)) => {
+                l.len() == r.len()
+                    && l.iter().zip(r).all(|((lk, lv), (rk, rv))| {
+                        lk == rk && values_equal_within(lv, rv, epsilon)
+                    })
+            }
+            (Value::Function(l), Value::Function(r)) => l == r,
+            (Value::Range { .. }, Value::Range { .. }) => left == right,
             (Value::Void, Value::Void) => true,
             _ => false,
         }
@@ -437,10 +1259,16 @@ pub mod utils {
             "⊟" | "⨂" => "-",
             "⊗" | "⊠" => "*",
             "⊘" | "⨸" => "/",
+            "⊛" => "%",
             "⊙" | "≡" => "==",
             "≢" => "!=",
             "◁" | "⊲" => "<",
             "▷" | "⊳" => ">",
+            "并" | "∧" => "&&",
+            "或" | "∨" => "||",
+            "¬" => "!",
+            "异或" => "^",
+            "蕴含" => "->",
             _ => op,
         }
     }
@@ -459,6 +1287,7 @@ pub mod utils {
                 | "⊠"
                 | "⊘"
                 | "⨸"
+                | "⊛"
                 | "⊙"
                 | "≡"
                 | "≢"
@@ -466,6 +1295,9 @@ pub mod utils {
                 | "⊲"
                 | "▷"
                 | "⊳"
+                | "∧"
+                | "∨"
+                | "¬"
         )
     }
 }
@@ -1,68 +1,204 @@
-use std::collections::HashMap;
+/// Value of a Chinese digit, simple (三) or financial (叁)
+fn digit_value(ch: char) -> Option<i64> {
+    Some(match ch {
+        '零' | '〇' => 0,
+        '一' | '壹' => 1,
+        '二' | '贰' => 2,
+        '三' | '叁' => 3,
+        '四' | '肆' => 4,
+        '五' | '伍' => 5,
+        '六' | '陆' => 6,
+        '七' | '柒' => 7,
+        '八' | '捌' => 8,
+        '九' | '玖' => 9,
+        _ => return None,
+    })
+}
+
+/// Value of a Chinese unit; 拾佰仟 are the financial forms of 十百千
+fn unit_value(ch: char) -> Option<i64> {
+    Some(match ch {
+        '十' | '拾' => 10,
+        '百' | '佰' => 100,
+        '千' | '仟' => 1000,
+        '万' => 10_000,
+        '亿' => 100_000_000,
+        '兆' => 1_000_000_000_000,
+        _ => return None,
+    })
+}
 
 pub fn chinese_to_number(s: &str) -> Option<i64> {
-    let digit_map: HashMap<char, i64> = [
-        ('零', 0),
-        ('〇', 0),
-        ('一', 1),
-        ('二', 2),
-        ('三', 3),
-        ('四', 4),
-        ('五', 5),
-        ('六', 6),
-        ('七', 7),
-        ('八', 8),
-        ('九', 9),
-    ]
-    .iter()
-    .cloned()
-    .collect();
-
-    let unit_map: HashMap<char, i64> = [('十', 10), ('百', 100), ('千', 1000), ('万', 10000)]
-        .iter()
-        .cloned()
-        .collect();
-
-    let chars: Vec<char> = s.chars().collect();
+    // A leading 负 (or 负数) negates the numeral after it: 负五 = -5
+    if let Some(rest) = s.trim_start().strip_prefix('负') {
+        let rest = rest.strip_prefix('数').unwrap_or(rest);
+        if rest.trim().is_empty() {
+            return None;
+        }
+        return chinese_to_number(rest)?.checked_neg();
+    }
+
+    let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+
+    // 又 joins a whole and a remainder, so 百又五 reads like 一百零五
+    if chars.contains(&'又') {
+        let joined: String = chars.iter().collect();
+        return joined.split('又').try_fold(0i64, |acc, part| {
+            if part.is_empty() {
+                return None;
+            }
+            acc.checked_add(chinese_to_number(part)?)
+        });
+    }
+
+    // Digit-by-digit readings with no units (一二三 = 123) are positional
+    if chars.len() > 1 && chars.iter().all(|&c| digit_value(c).is_some()) {
+        return chars.iter().try_fold(0i64, |acc, c| {
+            acc.checked_mul(10)?.checked_add(digit_value(*c)?)
+        });
+    }
 
     // Handle single digit
     if chars.len() == 1 {
-        if let Some(&val) = digit_map.get(&chars[0]) {
+        if let Some(val) = digit_value(chars[0]) {
             return Some(val);
         }
         // Handle standalone unit like "十" = 10
-        if let Some(&val) = unit_map.get(&chars[0]) {
+        if let Some(val) = unit_value(chars[0]) {
             return Some(val);
         }
     }
 
-    let mut result: i64 = 0;
+    // `total` holds the parts already scaled by 万, 亿 or 兆; `section` the
+    // 十/百/千 groups read since
+    let mut total: i64 = 0;
+    let mut section: i64 = 0;
     let mut current: i64 = 0;
-    let mut i = 0;
 
-    while i < chars.len() {
-        let ch = chars[i];
-
-        if let Some(&digit) = digit_map.get(&ch) {
+    for ch in chars {
+        if let Some(digit) = digit_value(ch) {
             current = digit;
-        } else if let Some(&unit) = unit_map.get(&ch) {
-            // Handle implicit "一" before unit
-            if current == 0 {
-                current = 1;
-            }
-
-            if unit >= 10000 {
-                // Handle 万
-                result = (result + current) * unit;
-                current = 0;
+        } else if let Some(unit) = unit_value(ch) {
+            if unit < 10_000 {
+                // Handle implicit "一" before unit
+                section = section.checked_add(current.max(1).checked_mul(unit)?)?;
             } else {
-                current *= unit;
-                result += current;
-                current = 0;
+                // A grouping unit scales everything read below it, so the 三千
+                // in 三千万亿 is scaled by 万 and then again by 亿
+                let below = total % unit;
+                let mut group = below.checked_add(section)?.checked_add(current)?;
+                if group == 0 {
+                    group = 1;
+                }
+                total = (total - below).checked_add(group.checked_mul(unit)?)?;
+                section = 0;
             }
+            current = 0;
         }
-        i += 1;
     }
 
-    Some(result + current)
+    total.checked_add(section)?.checked_add(current)
+}
+
+/// Parse a Chinese decimal, the whole part read like `chinese_to_number` and
+/// each digit after 点 as the next decimal place: 三点一四 = 3.14
+pub fn chinese_to_float(s: &str) -> Option<f64> {
+    let Some((whole, fraction)) = s.split_once('点') else {
+        return chinese_to_number(s).map(|n| n as f64);
+    };
+    if whole.trim().is_empty() || fraction.trim().is_empty() {
+        return None;
+    }
+
+    let negative = whole.trim_start().starts_with('负');
+    let whole = chinese_to_number(whole)?;
+    let mut decimal = whole.unsigned_abs().to_string();
+    decimal.push('.');
+    for ch in fraction.chars().filter(|c| !c.is_whitespace()) {
+        decimal.push(char::from_digit(digit_value(ch)? as u32, 10)?);
+    }
+
+    // Going through the decimal text rounds 三点一四 to the same f64 as 3.14
+    let value: f64 = decimal.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Parse a 第-prefixed Chinese ordinal (第三 = "the third") into its 1-based value
+pub fn parse_ordinal(s: &str) -> Option<i64> {
+    let numeral = s.strip_prefix('第')?;
+    if numeral.is_empty() {
+        return None;
+    }
+
+    numeral
+        .parse::<i64>()
+        .ok()
+        .or_else(|| chinese_to_number(numeral))
+}
+
+const DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+// Units of successive four-digit groups; 万兆 is 10^16
+const GROUP_UNITS: [&str; 5] = ["", "万", "亿", "兆", "万兆"];
+
+/// Render an integer as a Chinese numeral, the inverse of `chinese_to_number`
+/// for every value but `i64::MIN`: 105 = 一百零五, -12 = 负十二
+pub fn number_to_chinese(n: i64) -> String {
+    if n == 0 {
+        return "零".to_string();
+    }
+
+    let mut out = String::new();
+    if n < 0 {
+        out.push('负');
+    }
+
+    let mut rest = n.unsigned_abs();
+    let mut groups = Vec::new();
+    while rest > 0 {
+        groups.push((rest % 10_000) as usize);
+        rest /= 10_000;
+    }
+
+    let mut started = false;
+    let mut skipped = false;
+    for (i, &group) in groups.iter().enumerate().rev() {
+        if group == 0 {
+            skipped = true;
+            continue;
+        }
+        // A gap before a group (一万零一, 一亿零一千) is read as 零
+        if started && (skipped || group < 1000) {
+            out.push('零');
+        }
+        push_group(&mut out, group, !started);
+        out.push_str(GROUP_UNITS[i]);
+        started = true;
+        skipped = false;
+    }
+    out
+}
+
+/// Append one group of 1..=9999, with 零 for inner gaps. A leading group of
+/// 10..=19 drops its 一, as in 十五
+fn push_group(out: &mut String, group: usize, leading: bool) {
+    let digits = [group / 1000, group / 100 % 10, group / 10 % 10, group % 10];
+    let units = ["千", "百", "十", ""];
+    let mut started = false;
+    let mut gap = false;
+    for (position, &digit) in digits.iter().enumerate() {
+        if digit == 0 {
+            gap = started;
+            continue;
+        }
+        if gap {
+            out.push('零');
+            gap = false;
+        }
+        if !(leading && !started && position == 2 && digit == 1) {
+            out.push(DIGITS[digit]);
+        }
+        out.push_str(units[position]);
+        started = true;
+    }
 }
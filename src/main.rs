@@ -1,14 +1,14 @@
 use inkwell::context::Context;
-use ling_lang::{Environment, LingParser, Rule, Value};
+use ling_lang::codegen::Compiler;
+use ling_lang::executor::{destructure, unwrap_value};
+use ling_lang::parser::{self, Statement};
+use ling_lang::{Environment, LingConfig, LingParser, Rule, Value};
 use pest::Parser;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
 
-mod codegen;
-use codegen::{Compiler, StringPart};
-
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
@@ -47,41 +47,69 @@ fn compile_and_run(source_file: &str) -> Result<(), String> {
 
     // Parse
     println!("🔍 Parsing...");
+    parser::check_nesting(&source, LingConfig::default().max_parse_depth)
+        .map_err(|e| e.to_string())?;
+    let source = parser::canonicalize_glyphs(&source);
     let _pairs =
         LingParser::parse(Rule::PROGRAM, &source).map_err(|e| format!("Parse error: {}", e))?;
     println!("✓ Parsed successfully!\n");
 
     // Interpret for immediate feedback
     println!("🎭 Interpreting...");
-    interpret_program(&source)?;
+    if let Some(code) = interpret_program(&source)? {
+        println!("  Exit: {}", code);
+    }
     println!();
 
     // Compile to LLVM
     println!("⚙️  Compiling to LLVM IR...");
     let context = Context::create();
-    let mut compiler = Compiler::new(&context, "alien_module");
+    // LING_DEBUG_INFO=1 emits DWARF line tables so gdb/lldb can step by source line
+    let debug_info = std::env::var("LING_DEBUG_INFO").is_ok_and(|v| v != "0");
+    let mut compiler = Compiler::new(&context, "alien_module").with_debug_info(debug_info);
 
+    compiler.set_source_file(source_file);
     compiler.declare_stdlib();
     compiler.create_main_function();
 
     let pairs =
         LingParser::parse(Rule::PROGRAM, &source).map_err(|e| format!("Parse error: {}", e))?;
 
-    for pair in pairs {
+    let mut exited = false;
+    'program: for pair in pairs {
         if pair.as_rule() == Rule::PROGRAM {
             for statement_pair in pair.into_inner() {
                 if statement_pair.as_rule() != Rule::EOI {
-                    compile_statement(statement_pair, &mut compiler)?;
+                    let (line, column) = statement_pair.as_span().start_pos().line_col();
+                    compiler.set_source_location(line as u32, column as u32);
+                    let statement = match parser::parse_statement(statement_pair) {
+                        Ok(statement) => statement,
+                        Err(e) => {
+                            println!("Note: {}, not compiled", e);
+                            continue;
+                        }
+                    };
+                    if let Statement::FuncDef { .. } = statement {
+                        println!("Note: Function compilation not yet implemented");
+                        continue;
+                    }
+                    if compiler.compile_statement(&statement)? {
+                        exited = true;
+                        break 'program;
+                    }
                 }
             }
         }
     }
 
-    compiler.finish_main();
+    // An exit statement has already terminated main with its own status
+    if !exited {
+        compiler.finish_main();
+    }
 
     // Output files - place compiled artifacts in an output directory
-    let output_dir = std::env::var("LING_OUTPUT_DIR")
-        .unwrap_or_else(|_| "tests/test_compiled".to_string());
+    let output_dir =
+        std::env::var("LING_OUTPUT_DIR").unwrap_or_else(|_| "tests/test_compiled".to_string());
 
     fs::create_dir_all(&output_dir)
         .map_err(|e| format!("Failed to create output directory '{}': {}", output_dir, e))?;
@@ -130,8 +158,8 @@ fn compile_and_run(source_file: &str) -> Result<(), String> {
     Ok(())
 }
 
-// Interpreter for immediate feedback
-fn interpret_program(source: &str) -> Result<(), String> {
+// Interpreter for immediate feedback; returns the exit status if the program exited early
+fn interpret_program(source: &str) -> Result<Option<i64>, String> {
     let pairs =
         LingParser::parse(Rule::PROGRAM, source).map_err(|e| format!("Parse error: {}", e))?;
 
@@ -141,14 +169,20 @@ fn interpret_program(source: &str) -> Result<(), String> {
     for pair in pairs {
         if pair.as_rule() == Rule::PROGRAM {
             for statement_pair in pair.into_inner() {
-                if statement_pair.as_rule() != Rule::EOI {
-                    interpret_statement(statement_pair, &mut env, &mut function_defs)?;
+                if statement_pair.as_rule() == Rule::EOI {
+                    continue;
+                }
+                let (line, column) = statement_pair.as_span().start_pos().line_col();
+                let exit = interpret_statement(statement_pair, &mut env, &mut function_defs)
+                    .map_err(|e| format!("{} at line {}, col {}", e, line, column))?;
+                if let Some(code) = exit {
+                    return Ok(Some(code));
                 }
             }
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
 #[derive(Clone, Debug)]
@@ -162,44 +196,70 @@ fn interpret_statement(
     pair: pest::iterators::Pair<Rule>,
     env: &mut Environment,
     functions: &mut HashMap<String, FunctionDef>,
-) -> Result<(), String> {
+) -> Result<Option<i64>, String> {
     match pair.as_rule() {
         Rule::STATEMENT => {
             for inner in pair.into_inner() {
-                interpret_statement(inner, env, functions)?;
+                if let Some(code) = interpret_statement(inner, env, functions)? {
+                    return Ok(Some(code));
+                }
             }
         }
         Rule::VAR_DECL => {
             let mut inner = pair.into_inner();
             inner.next(); // Skip LET_KW (变量, 变, ⟡, ◈, etc.)
 
-            let var_name = inner
-                .next()
-                .ok_or("Missing variable name")?
-                .as_str()
-                .to_string();
-
-            inner.next(); // Skip ASSIGN_OP (=, ⇐, ⟸)
-
-            let value_pair = inner.next().ok_or("Missing value")?;
-
-            let value = evaluate_expression(value_pair, env)?;
-            env.set(var_name.clone(), value.clone());
+            let mut var_names = Vec::new();
+            let mut initializer = None;
+            for part in inner {
+                match part.as_rule() {
+                    Rule::VAR_NAME => var_names.push(part.as_str().to_string()),
+                    Rule::EXPRESSION => initializer = Some(part),
+                    _ => {} // Skip ASSIGN_OP (=, ⇐, ⟸)
+                }
+            }
 
-            println!("  {} = {}", var_name, value);
+            // A bare declaration starts out Void
+            let bindings = match initializer {
+                Some(value_pair) => {
+                    let value_pair = unwrap_value(value_pair).map_err(|e| e.to_string())?;
+                    let value = evaluate_expression(value_pair, env)?;
+                    destructure(var_names, value).map_err(|e| e.to_string())?
+                }
+                None => var_names
+                    .into_iter()
+                    .map(|name| (name, Value::Void))
+                    .collect(),
+            };
+            for (var_name, value) in bindings {
+                env.set(var_name.clone(), value.clone());
+                println!("  {} = {}", var_name, value);
+            }
         }
-        Rule::PRINT_STMT => {
-            let mut inner = pair.into_inner();
-            inner.next(); // Skip PRINT_KW (输出, ⟲, ◉)
-
-            let value_pair = inner.next().ok_or("Missing print value")?;
-
-            let value = evaluate_expression(value_pair, env)?;
-            println!("  Output: {}", value);
+        Rule::PRINT_STMT | Rule::ERR_PRINT_STMT => {
+            let to_stderr = pair.as_rule() == Rule::ERR_PRINT_STMT;
+            let mut values = Vec::new();
+            for value_pair in pair
+                .into_inner()
+                .filter(|p| p.as_rule() == Rule::EXPRESSION)
+            {
+                let value_pair = unwrap_value(value_pair).map_err(|e| e.to_string())?;
+                values.push(evaluate_expression(value_pair, env)?.to_string());
+            }
+            if values.is_empty() {
+                return Err("Missing print value".to_string());
+            }
+            if to_stderr {
+                eprintln!("  Error output: {}", values.join(" "));
+            } else {
+                println!("  Output: {}", values.join(" "));
+            }
         }
         Rule::FUNC_DEF => {
-            let mut inner = pair.into_inner();
-            inner.next(); // Skip FUNC_KW (⟡, 函数)
+            // Skip FUNC_KW (⟡, 函数) and any MEMO_KW (⟐, 记忆) or @ attributes before it
+            let mut inner = pair.into_inner().skip_while(|p| {
+                matches!(p.as_rule(), Rule::ATTRIBUTE | Rule::MEMO_KW | Rule::FUNC_KW)
+            });
 
             let func_name = inner
                 .next()
@@ -234,28 +294,45 @@ fn interpret_statement(
             println!("  Defined function: {}({:?})", func_name, params);
         }
         Rule::IF_STMT | Rule::ALIEN_IF_STMT | Rule::TRAD_IF_STMT => {
-            interpret_if_statement(pair, env, functions)?;
+            return interpret_if_statement(pair, env, functions);
         }
         Rule::RETURN_STMT => {
             let mut inner = pair.into_inner();
             inner.next(); // Skip RETURN_KW (⟴, 返回)
 
-            if let Some(expr) = inner.next() {
-                let value = evaluate_expression(expr, env)?;
-                println!("  Return: {}", value);
+            let mut values = Vec::new();
+            for expr in inner.filter(|p| p.as_rule() == Rule::EXPRESSION) {
+                values.push(evaluate_expression(expr, env)?);
             }
+            match values.len() {
+                0 => {}
+                1 => println!("  Return: {}", values[0]),
+                _ => println!("  Return: {}", Value::Array(values)),
+            }
+        }
+        Rule::EXIT_STMT => {
+            let mut inner = pair.into_inner();
+            inner.next(); // Skip EXIT_KW (⏏, 退出)
+
+            let code = match inner.next() {
+                Some(expr) if expr.as_rule() == Rule::EXPRESSION => evaluate_expression(expr, env)?
+                    .to_number()
+                    .ok_or("Exit status must be a number")?,
+                _ => 0,
+            };
+            return Ok(Some(code));
         }
         _ => {}
     }
 
-    Ok(())
+    Ok(None)
 }
 
 fn interpret_if_statement(
     pair: pest::iterators::Pair<Rule>,
     env: &mut Environment,
     functions: &mut HashMap<String, FunctionDef>,
-) -> Result<(), String> {
+) -> Result<Option<i64>, String> {
     // IF_STMT wraps ALIEN_IF_STMT or TRAD_IF_STMT — unwrap it
     let actual_pair = if pair.as_rule() == Rule::IF_STMT {
         pair.into_inner().next().ok_or("Empty IF_STMT")?
@@ -314,17 +391,14 @@ fn interpret_if_statement(
     }
 
     // Execute appropriate block
-    if is_true {
-        for stmt in then_stmts {
-            interpret_statement(stmt, env, functions)?;
-        }
-    } else {
-        for stmt in else_stmts {
-            interpret_statement(stmt, env, functions)?;
+    let stmts = if is_true { then_stmts } else { else_stmts };
+    for stmt in stmts {
+        if let Some(code) = interpret_statement(stmt, env, functions)? {
+            return Ok(Some(code));
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
 fn evaluate_expression(
@@ -334,6 +408,10 @@ fn evaluate_expression(
     match pair.as_rule() {
         Rule::VALUE
         | Rule::EXPRESSION
+        | Rule::IMPLIES_EXPR
+        | Rule::OR_EXPR
+        | Rule::XOR_EXPR
+        | Rule::AND_EXPR
         | Rule::CONCAT_EXPR
         | Rule::COMPARISON
         | Rule::ADD_EXPR
@@ -348,14 +426,11 @@ fn evaluate_expression(
             let inner = pair.into_inner().next().ok_or("Empty primary")?;
             evaluate_expression(inner, env)
         }
-        Rule::NUMBER => Ok(Value::Number(parse_number(pair.as_str()))),
-        Rule::OPERATOR_NUMBER => Ok(Value::Number(parse_number(pair.as_str()))),
-        Rule::OPERATOR_SYMBOL => Ok(Value::Number(parse_operator_literal(pair.as_str()))),
-        Rule::STRING => {
-            let s = pair.as_str();
-            let content = extract_string_content(s);
-            Ok(Value::String(content))
-        }
+        Rule::NUMBER
+        | Rule::OPERATOR_NUMBER
+        | Rule::OPERATOR_LITERAL
+        | Rule::OPERATOR_SYMBOL
+        | Rule::STRING => parser::parse_value(pair, env).map_err(|e| e.to_string()),
         Rule::VAR_NAME => {
             let var_name = pair.as_str();
             env.get(var_name)
@@ -368,224 +443,3 @@ fn evaluate_expression(
         _ => Err(format!("Unknown expression type: {:?}", pair.as_rule())),
     }
 }
-
-fn compile_statement(
-    pair: pest::iterators::Pair<Rule>,
-    compiler: &mut Compiler,
-) -> Result<(), String> {
-    match pair.as_rule() {
-        Rule::STATEMENT => {
-            for inner in pair.into_inner() {
-                compile_statement(inner, compiler)?;
-            }
-        }
-        Rule::VAR_DECL => {
-            let mut inner = pair.into_inner();
-            inner.next(); // Skip LET_KW
-
-            let var_name = inner.next().ok_or("Missing variable name")?.as_str();
-
-            inner.next(); // Skip ASSIGN_OP
-
-            let value_pair = inner.next().ok_or("Missing value")?;
-
-            let parts = extract_string_parts(value_pair);
-
-            if parts.len() == 1 {
-                match &parts[0] {
-                    StringPart::Literal(s) => {
-                        compiler.store_string(var_name, s);
-                    }
-                    StringPart::Variable(v) => {
-                        // Copy variable value
-                        if let Some(_val) = compiler.get_variable(v) {
-                            // TODO: Handle based on type
-                        }
-                    }
-                }
-            } else {
-                compiler.concat_strings(var_name, parts);
-            }
-        }
-        Rule::PRINT_STMT => {
-            let mut inner = pair.into_inner();
-            inner.next(); // Skip PRINT_KW
-
-            let value_pair = inner.next().ok_or("Missing print value")?;
-
-            let var_name = extract_var_name(value_pair)?;
-            compiler.print_variable(&var_name);
-        }
-        Rule::FUNC_DEF => {
-            println!("Note: Function compilation not yet implemented");
-        }
-        Rule::IF_STMT | Rule::ALIEN_IF_STMT | Rule::TRAD_IF_STMT => {
-            println!("Note: If-statement compilation not yet implemented");
-        }
-        Rule::WHILE_STMT => {
-            println!("Note: While-loop compilation not yet implemented");
-        }
-        _ => {}
-    }
-
-    Ok(())
-}
-
-fn extract_string_parts(pair: pest::iterators::Pair<Rule>) -> Vec<StringPart> {
-    let mut parts = Vec::new();
-
-    match pair.as_rule() {
-        Rule::VALUE
-        | Rule::EXPRESSION
-        | Rule::ARITHMETIC_EXPR
-        | Rule::COMPARISON
-        | Rule::ADD_EXPR
-        | Rule::MULT_EXPR
-        | Rule::PRIMARY => {
-            // Unwrap single-child wrapper rules
-            if let Some(inner) = pair.into_inner().next() {
-                parts.extend(extract_string_parts(inner));
-            }
-        }
-        Rule::CONCAT_EXPR => {
-            for child in pair.into_inner() {
-                match child.as_rule() {
-                    Rule::CONCAT_OP => continue,
-                    _ => parts.extend(extract_string_parts(child)),
-                }
-            }
-        }
-        Rule::TERM => {
-            parts.extend(extract_term_parts(pair));
-        }
-        Rule::STRING => {
-            let content = extract_string_content(pair.as_str());
-            parts.push(StringPart::Literal(content));
-        }
-        Rule::NUMBER | Rule::OPERATOR_NUMBER => {
-            let num_value = parse_number(pair.as_str()).to_string();
-            parts.push(StringPart::Literal(num_value));
-        }
-        Rule::OPERATOR_SYMBOL => {
-            let num_value = parse_operator_literal(pair.as_str()).to_string();
-            parts.push(StringPart::Literal(num_value));
-        }
-        Rule::VAR_NAME => {
-            parts.push(StringPart::Variable(pair.as_str().to_string()));
-        }
-        _ => {
-            if let Some(inner) = pair.into_inner().next() {
-                parts.extend(extract_string_parts(inner));
-            }
-        }
-    }
-
-    parts
-}
-
-fn extract_term_parts(pair: pest::iterators::Pair<Rule>) -> Vec<StringPart> {
-    match pair.as_rule() {
-        Rule::TERM => {
-            if let Some(inner) = pair.into_inner().next() {
-                extract_term_parts(inner)
-            } else {
-                Vec::new()
-            }
-        }
-        Rule::STRING => {
-            let content = extract_string_content(pair.as_str());
-            vec![StringPart::Literal(content)]
-        }
-        Rule::NUMBER | Rule::OPERATOR_NUMBER => {
-            let num_value = parse_number(pair.as_str()).to_string();
-            vec![StringPart::Literal(num_value)]
-        }
-        Rule::OPERATOR_SYMBOL => {
-            let num_value = parse_operator_literal(pair.as_str()).to_string();
-            vec![StringPart::Literal(num_value)]
-        }
-        Rule::VAR_NAME => {
-            vec![StringPart::Variable(pair.as_str().to_string())]
-        }
-        _ => Vec::new(),
-    }
-}
-
-fn extract_var_name(pair: pest::iterators::Pair<Rule>) -> Result<String, String> {
-    match pair.as_rule() {
-        Rule::VALUE
-        | Rule::EXPRESSION
-        | Rule::CONCAT_EXPR
-        | Rule::COMPARISON
-        | Rule::ADD_EXPR
-        | Rule::MULT_EXPR
-        | Rule::PRIMARY
-        | Rule::ARITHMETIC_EXPR
-        | Rule::TERM => {
-            let inner = pair.into_inner().next().ok_or("Empty value")?;
-            extract_var_name(inner)
-        }
-        Rule::VAR_NAME => Ok(pair.as_str().to_string()),
-        _ => Err(format!("Expected variable name, got {:?}", pair.as_rule())),
-    }
-}
-
-fn parse_number(s: &str) -> i64 {
-    // Try ASCII
-    if let Ok(n) = s.parse::<i64>() {
-        return n;
-    }
-
-    // Repeated operators (⊕⊕⊕⊕⊕ = 5)
-    if let Some(first_char) = s.chars().next() {
-        if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸∀∃∄∅".contains(first_char) {
-            let count = s.chars().filter(|&c| c == first_char).count();
-            if count == s.chars().count() {
-                return count as i64;
-            }
-        }
-    }
-
-    // Try Chinese numbers
-    if let Some(n) = ling_lang::ling_number::chinese_to_number(s) {
-        return n;
-    }
-
-    0
-}
-
-fn parse_operator_literal(op: &str) -> i64 {
-    match op {
-        "⊕" => 1,
-        "⊗" => 2,
-        "⊘" => 0,
-        "⊚" => 10,
-        "⊙" => 5,
-        "⊞" => 1,
-        "⊟" => 0,
-        "⊠" => 2,
-        "⨁" => 1,
-        "⨂" => 0,
-        "⨸" => 0,
-        "∀" => 3,
-        "∃" => 2,
-        "∄" => 1,
-        "∅" => 0,
-        _ => 0,
-    }
-}
-
-fn extract_string_content(s: &str) -> String {
-    if s.starts_with('"') && s.ends_with('"') {
-        s[1..s.len() - 1].to_string()
-    } else if s.starts_with('⟦') && s.ends_with('⟧') {
-        // UTF-8: ⟦ is 3 bytes, ⟧ is 3 bytes
-        let bytes = s.as_bytes();
-        String::from_utf8_lossy(&bytes[3..bytes.len() - 3]).to_string()
-    } else if s.starts_with('⟨') && s.ends_with('⟩') {
-        let bytes = s.as_bytes();
-        String::from_utf8_lossy(&bytes[3..bytes.len() - 3]).to_string()
-    } else {
-        s.to_string()
-    }
-}
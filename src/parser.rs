@@ -1,10 +1,20 @@
-use crate::{Environment, LingParser, Rule, Value};
+use crate::{Environment, LingConfig, LingError, LingParser, LingResult, Rule, Value, ling_number};
 use pest::Parser;
-use pest::iterators::Pair;
+use pest::iterators::{Pair, Pairs};
+use std::collections::HashSet;
+use std::num::IntErrorKind;
 
 pub fn parse_program(input: &str) -> Result<Vec<Statement>, String> {
+    let statements = parse_program_spanned(input)?;
+    Ok(statements.into_iter().map(|s| s.statement).collect())
+}
+
+/// Parse a program, keeping where in the source each top-level statement came from
+pub fn parse_program_spanned(input: &str) -> Result<Vec<SpannedStatement>, String> {
+    check_nesting(input, LingConfig::default().max_parse_depth).map_err(|e| e.to_string())?;
+    let input = canonicalize_glyphs(input);
     let pairs =
-        LingParser::parse(Rule::PROGRAM, input).map_err(|e| format!("Parse error: {}", e))?;
+        LingParser::parse(Rule::PROGRAM, &input).map_err(|e| format!("Parse error: {}", e))?;
 
     let mut statements = Vec::new();
 
@@ -12,7 +22,7 @@ pub fn parse_program(input: &str) -> Result<Vec<Statement>, String> {
         if pair.as_rule() == Rule::PROGRAM {
             for inner in pair.into_inner() {
                 if inner.as_rule() == Rule::STATEMENT {
-                    statements.push(parse_statement(inner)?);
+                    statements.push(parse_statement_spanned(inner)?);
                 }
             }
         }
@@ -21,23 +31,127 @@ pub fn parse_program(input: &str) -> Result<Vec<Statement>, String> {
     Ok(statements)
 }
 
+/// Reject sources whose brackets nest deeper than `max_depth`, before pest
+/// and the AST builders recurse into them and overflow the stack. Brackets
+/// inside string literals don't count
+pub fn check_nesting(input: &str, max_depth: usize) -> LingResult<()> {
+    let mut depth = 0usize;
+    let mut closing_quote = None;
+    for c in input.chars() {
+        if let Some(quote) = closing_quote {
+            if c == quote {
+                closing_quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' => closing_quote = Some('"'),
+            '⟦' => closing_quote = Some('⟧'),
+            '⟨' => closing_quote = Some('⟩'),
+            '(' | '⟮' | '⦅' | '[' | '⟬' | '{' | '⦃' | '⟪' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(LingError::ParseError(
+                        "expression nesting too deep".to_string(),
+                    ));
+                }
+            }
+            ')' | '⟯' | '⦆' | ']' | '⟭' | '}' | '⦄' | '⟫' => {
+                depth = depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Look-alikes of the grammar's glyphs, each with the canonical glyph it is
+/// read as. Both sides encode to three UTF-8 bytes, so spans into the
+/// canonical source still point at the same text in the original. Glyphs
+/// that may start an identifier (`ALIEN_ID_START`, e.g. ☉ and ◇) are left
+/// out, since rewriting them would change which variable a program names
+const LOOKALIKE_GLYPHS: &[(char, char)] = &[
+    ('ⴲ', '⊕'),
+    ('⮾', '⊗'),
+    ('⦻', '⊗'),
+    ('⮽', '⊠'),
+    ('⨀', '⊙'),
+    ('⬦', '⋄'),
+    ('↺', '⟲'),
+    ('⇦', '⇐'),
+    ('⇨', '⇒'),
+];
+
+/// Replace pasted look-alikes of operator glyphs with the glyphs the grammar
+/// expects. String literals are left as written
+pub fn canonicalize_glyphs(source: &str) -> String {
+    let mut closing_quote = None;
+    source
+        .chars()
+        .map(|c| {
+            if let Some(quote) = closing_quote {
+                if c == quote {
+                    closing_quote = None;
+                }
+                return c;
+            }
+            closing_quote = match c {
+                '"' => Some('"'),
+                '⟦' => Some('⟧'),
+                '⟨' => Some('⟩'),
+                _ => None,
+            };
+            LOOKALIKE_GLYPHS
+                .iter()
+                .find(|(lookalike, _)| *lookalike == c)
+                .map_or(c, |(_, canonical)| *canonical)
+        })
+        .collect()
+}
+
+/// Byte offsets of a statement in its source, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A parsed statement with its source span, for diagnostics
+#[derive(Debug, Clone)]
+pub struct SpannedStatement {
+    pub statement: Statement,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum Statement {
     VarDecl {
         name: String,
         value: Expression,
     },
+    /// A constant; `literal` is set when its initializer folded to a literal
+    ConstDecl {
+        name: String,
+        value: Expression,
+        literal: bool,
+    },
     Print {
         expr: Expression,
     },
+    /// A function; `attributes` are the names of its @ modifiers, with the
+    /// 记忆 keyword recorded as the 记忆 attribute
     FuncDef {
         name: String,
         params: Vec<String>,
         body: Vec<Statement>,
+        attributes: Vec<String>,
     },
     Return {
         expr: Option<Expression>,
     },
+    Exit {
+        expr: Option<Expression>,
+    },
     If {
         condition: Expression,
         then_block: Vec<Statement>,
@@ -57,6 +171,18 @@ pub enum Expression {
     },
 }
 
+/// Parse a statement like `parse_statement`, recording its span
+pub fn parse_statement_spanned(pair: Pair<Rule>) -> Result<SpannedStatement, String> {
+    let span = Span {
+        start: pair.as_span().start(),
+        end: pair.as_span().end(),
+    };
+    Ok(SpannedStatement {
+        statement: parse_statement(pair)?,
+        span,
+    })
+}
+
 pub fn parse_statement(pair: Pair<Rule>) -> Result<Statement, String> {
     let inner = pair.into_inner().next().unwrap();
 
@@ -65,6 +191,7 @@ pub fn parse_statement(pair: Pair<Rule>) -> Result<Statement, String> {
         Rule::PRINT_STMT => parse_print_stmt(inner),
         Rule::FUNC_DEF => parse_func_def(inner),
         Rule::RETURN_STMT => parse_return_stmt(inner),
+        Rule::EXIT_STMT => parse_exit_stmt(inner),
         Rule::IF_STMT | Rule::ALIEN_IF_STMT => parse_if_stmt(inner),
         _ => Err(format!("Unknown statement: {:?}", inner.as_rule())),
     }
@@ -72,26 +199,196 @@ pub fn parse_statement(pair: Pair<Rule>) -> Result<Statement, String> {
 
 fn parse_var_decl(pair: Pair<Rule>) -> Result<Statement, String> {
     let mut inner = pair.into_inner();
-    inner.next(); // Skip LET_KW
+    let is_const = inner.next().unwrap().as_rule() == Rule::CONST_KW;
 
     let name = inner.next().unwrap().as_str().to_string();
-    inner.next(); // Skip ASSIGN_OP
-    let value = parse_expression(inner.next().unwrap())?;
-
+    if inner.peek().is_some_and(|p| p.as_rule() == Rule::VAR_NAME) {
+        return Err("Declaring several names at once is not supported yet".to_string());
+    }
+    let value = match inner.find(|p| p.as_rule() == Rule::EXPRESSION) {
+        Some(expr) => parse_expression(expr)?,
+        None => return Err(format!("Declaration of '{}' has no initializer", name)),
+    };
+
+    if is_const {
+        let value = fold_constants(value);
+        let literal = matches!(value, Expression::Number(_) | Expression::String(_));
+        return Ok(Statement::ConstDecl {
+            name,
+            value,
+            literal,
+        });
+    }
     Ok(Statement::VarDecl { name, value })
 }
 
+/// Fold arithmetic on number literals (⊕ ⊞ ⊗ → 3), leaving anything that
+/// involves variables, comparisons or overflow as it is
+pub fn fold_constants(expr: Expression) -> Expression {
+    let Expression::BinaryOp { op, left, right } = expr else {
+        return expr;
+    };
+    let left = fold_constants(*left);
+    let right = fold_constants(*right);
+
+    if let (Expression::Number(l), Expression::Number(r)) = (&left, &right) {
+        let folded = match op.as_str() {
+            "+" | "⊞" | "⨁" => l.checked_add(*r),
+            "-" | "⊟" | "⨂" => l.checked_sub(*r),
+            "*" | "⊠" | "⊗" => l.checked_mul(*r),
+            "/" | "⊘" | "⨸" => l.checked_div(*r),
+            "%" | "⊛" => l.checked_rem(*r),
+            _ => None,
+        };
+        if let Some(n) = folded {
+            return Expression::Number(n);
+        }
+    }
+
+    Expression::BinaryOp {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+/// Names a function body reads without binding them itself: what a closure
+/// over it has to capture. Locals count as bound from their declaration on,
+/// and nested functions see their own parameters as bound
+pub fn free_variables(body: &[Statement]) -> Vec<String> {
+    let mut free = Vec::new();
+    collect_free(body, &mut HashSet::new(), &mut free);
+    free
+}
+
+/// Names a parsed function body may read from where it is defined, so the
+/// closure captures just those (see `Environment::capture_free_vars`). Uses
+/// `free_variables` when the body fits the `Statement` AST, and otherwise
+/// every name the body mentions. Parameters are bound by the call
+pub fn body_free_variables(body: Pairs<Rule>, params: &[String]) -> Vec<String> {
+    let statements: Vec<_> = body
+        .flat_map(|program| program.into_inner())
+        .filter(|p| p.as_rule() == Rule::STATEMENT)
+        .collect();
+    let names = match statements
+        .iter()
+        .cloned()
+        .map(parse_statement)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(ast) => free_variables(&ast),
+        Err(_) => {
+            let mut names = Vec::new();
+            for statement in statements {
+                collect_variables(statement, &mut names);
+            }
+            names
+        }
+    };
+    names
+        .into_iter()
+        .filter(|name| !params.contains(name))
+        .collect()
+}
+
+/// Names an expression reads, in order of first mention. Map keys after `.`
+/// are not reads; called function names and lambda parameters are, which
+/// only ever over-approximates
+pub fn expression_variables(pair: Pair<Rule>) -> Vec<String> {
+    let mut names = Vec::new();
+    collect_variables(pair, &mut names);
+    names
+}
+
+fn collect_variables(pair: Pair<Rule>, names: &mut Vec<String>) {
+    match pair.as_rule() {
+        Rule::MEMBER => {}
+        Rule::VAR_NAME => {
+            let name = pair.as_str().to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        _ => pair
+            .into_inner()
+            .for_each(|inner| collect_variables(inner, names)),
+    }
+}
+
+fn collect_free(body: &[Statement], bound: &mut HashSet<String>, free: &mut Vec<String>) {
+    for statement in body {
+        match statement {
+            Statement::VarDecl { name, value } | Statement::ConstDecl { name, value, .. } => {
+                collect_free_expr(value, bound, free);
+                bound.insert(name.clone());
+            }
+            Statement::Print { expr }
+            | Statement::Return { expr: Some(expr) }
+            | Statement::Exit { expr: Some(expr) } => collect_free_expr(expr, bound, free),
+            Statement::Return { expr: None } | Statement::Exit { expr: None } => {}
+            Statement::FuncDef {
+                name, params, body, ..
+            } => {
+                // Bound before the body so recursive calls are not free
+                bound.insert(name.clone());
+                let mut inner = bound.clone();
+                inner.extend(params.iter().cloned());
+                collect_free(body, &mut inner, free);
+            }
+            Statement::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                collect_free_expr(condition, bound, free);
+                collect_free(then_block, &mut bound.clone(), free);
+                if let Some(else_block) = else_block {
+                    collect_free(else_block, &mut bound.clone(), free);
+                }
+            }
+        }
+    }
+}
+
+fn collect_free_expr(expr: &Expression, bound: &HashSet<String>, free: &mut Vec<String>) {
+    match expr {
+        Expression::Variable(name) => {
+            if !bound.contains(name) && !free.contains(name) {
+                free.push(name.clone());
+            }
+        }
+        Expression::BinaryOp { left, right, .. } => {
+            collect_free_expr(left, bound, free);
+            collect_free_expr(right, bound, free);
+        }
+        Expression::Number(_) | Expression::String(_) => {}
+    }
+}
+
 fn parse_print_stmt(pair: Pair<Rule>) -> Result<Statement, String> {
     let mut inner = pair.into_inner();
     inner.next(); // Skip PRINT_KW
     let expr = parse_expression(inner.next().unwrap())?;
+    if inner.any(|p| p.as_rule() == Rule::EXPRESSION) {
+        return Err("Printing several values is not supported yet".to_string());
+    }
 
     Ok(Statement::Print { expr })
 }
 
 fn parse_func_def(pair: Pair<Rule>) -> Result<Statement, String> {
-    let mut inner = pair.into_inner();
-    inner.next(); // Skip FUNC_KW
+    // Collect the modifiers up to and including FUNC_KW
+    let mut inner = pair.into_inner().peekable();
+    let mut attributes = Vec::new();
+    while let Some(modifier) =
+        inner.next_if(|p| matches!(p.as_rule(), Rule::ATTRIBUTE | Rule::MEMO_KW | Rule::FUNC_KW))
+    {
+        match modifier.as_rule() {
+            Rule::ATTRIBUTE => attributes.push(modifier.as_str()[1..].to_string()),
+            Rule::MEMO_KW => attributes.push("记忆".to_string()),
+            _ => {}
+        }
+    }
 
     let name = inner.next().unwrap().as_str().to_string();
     inner.next(); // Skip BLOCK_START
@@ -118,61 +415,65 @@ fn parse_func_def(pair: Pair<Rule>) -> Result<Statement, String> {
         }
     }
 
-    Ok(Statement::FuncDef { name, params, body })
+    Ok(Statement::FuncDef {
+        name,
+        params,
+        body,
+        attributes,
+    })
 }
 
 fn parse_return_stmt(pair: Pair<Rule>) -> Result<Statement, String> {
-    let mut inner = pair.into_inner();
-    inner.next(); // Skip RETURN_KW
-
-    let expr = inner.next().map(|p| parse_expression(p)).transpose()?;
+    let mut exprs = pair
+        .into_inner()
+        .filter(|p| p.as_rule() == Rule::EXPRESSION);
+    let expr = exprs.next().map(parse_expression).transpose()?;
+    if exprs.next().is_some() {
+        return Err("Returning several values is not supported yet".to_string());
+    }
     Ok(Statement::Return { expr })
 }
 
-fn parse_if_stmt(pair: Pair<Rule>) -> Result<Statement, String> {
-    let mut inner = pair.into_inner();
-    inner.next(); // Skip IF_KW
-
-    let condition = parse_expression(inner.next().unwrap())?;
+fn parse_exit_stmt(pair: Pair<Rule>) -> Result<Statement, String> {
+    let expr = pair
+        .into_inner()
+        .find(|p| p.as_rule() == Rule::EXPRESSION)
+        .map(parse_expression)
+        .transpose()?;
+    Ok(Statement::Exit { expr })
+}
 
-    // Skip THEN_KW or find BLOCK_START
-    while let Some(next) = inner.next() {
-        if next.as_rule() == Rule::BLOCK_START {
-            break;
-        }
-    }
+fn parse_if_stmt(pair: Pair<Rule>) -> Result<Statement, String> {
+    // IF_STMT wraps ALIEN_IF_STMT or TRAD_IF_STMT — unwrap it
+    let pair = if pair.as_rule() == Rule::IF_STMT {
+        pair.into_inner().next().ok_or("Empty IF_STMT")?
+    } else {
+        pair
+    };
 
+    let mut condition = None;
     let mut then_block = Vec::new();
     let mut else_block = None;
 
-    // Parse then block
-    for stmt_pair in inner.by_ref() {
-        if stmt_pair.as_rule() == Rule::BLOCK_END {
-            break;
-        }
-        if stmt_pair.as_rule() == Rule::STATEMENT {
-            then_block.push(parse_statement(stmt_pair)?);
-        }
-    }
-
-    // Check for else block
-    if let Some(else_kw) = inner.next() {
-        if matches!(else_kw.as_rule(), Rule::ELSE_KW) {
-            inner.next(); // Skip BLOCK_START
-            let mut else_stmts = Vec::new();
-
-            for stmt_pair in inner {
-                if stmt_pair.as_rule() == Rule::STATEMENT {
-                    else_stmts.push(parse_statement(stmt_pair)?);
+    for child in pair.into_inner() {
+        match child.as_rule() {
+            Rule::EXPRESSION if condition.is_none() => {
+                condition = Some(parse_expression(child)?);
+            }
+            Rule::ELSE_KW => else_block = Some(Vec::new()),
+            Rule::STATEMENT => {
+                let statement = parse_statement(child)?;
+                match else_block.as_mut() {
+                    Some(else_stmts) => else_stmts.push(statement),
+                    None => then_block.push(statement),
                 }
             }
-
-            else_block = Some(else_stmts);
+            _ => {}
         }
     }
 
     Ok(Statement::If {
-        condition,
+        condition: condition.ok_or("Missing condition")?,
         then_block,
         else_block,
     })
@@ -182,7 +483,7 @@ fn parse_expression(pair: Pair<Rule>) -> Result<Expression, String> {
     match pair.as_rule() {
         Rule::NUMBER => {
             let num_str = pair.as_str();
-            let value = parse_number(num_str);
+            let value = parse_number(num_str)?;
             Ok(Expression::Number(value))
         }
         Rule::STRING => {
@@ -191,15 +492,26 @@ fn parse_expression(pair: Pair<Rule>) -> Result<Expression, String> {
             Ok(Expression::String(content))
         }
         Rule::VAR_NAME => Ok(Expression::Variable(pair.as_str().to_string())),
-        Rule::OPERATOR_SYMBOL => {
+        Rule::OPERATOR_LITERAL | Rule::OPERATOR_SYMBOL => {
             let value = parse_operator_literal(pair.as_str());
             Ok(Expression::Number(value))
         }
-        Rule::COMPARISON | Rule::ADD_EXPR | Rule::MULT_EXPR => parse_binary_expr(pair),
+        Rule::IMPLIES_EXPR
+        | Rule::OR_EXPR
+        | Rule::XOR_EXPR
+        | Rule::AND_EXPR
+        | Rule::CONCAT_EXPR
+        | Rule::COMPARISON
+        | Rule::ADD_EXPR
+        | Rule::MULT_EXPR => parse_binary_expr(pair),
+        Rule::NOT_EXPR => Err("Logical negation is not supported yet".to_string()),
         _ => {
-            // Try to parse as primary or nested expression
+            // Try to parse as primary or nested (possibly parenthesized) expression
             let rule = pair.as_rule();
-            if let Some(inner) = pair.into_inner().next() {
+            let mut inner = pair
+                .into_inner()
+                .filter(|p| !matches!(p.as_rule(), Rule::PAREN_OPEN | Rule::PAREN_CLOSE));
+            if let Some(inner) = inner.next() {
                 parse_expression(inner)
             } else {
                 Err(format!("Unknown expression type: {:?}", rule))
@@ -225,109 +537,63 @@ fn parse_binary_expr(pair: Pair<Rule>) -> Result<Expression, String> {
     Ok(left)
 }
 
-fn parse_number(s: &str) -> i64 {
+fn parse_number(s: &str) -> Result<i64, String> {
+    if s.contains('点') {
+        return Err("Decimal numbers are not supported yet".to_string());
+    }
+
     // Try ASCII
-    if let Ok(n) = s.parse::<i64>() {
-        return n;
+    match s.parse::<i64>() {
+        Ok(n) => return Ok(n),
+        Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow) => {
+            return Err("number too large".to_string());
+        }
+        Err(_) => {}
     }
 
-    // Repeated operators (⊕⊕⊕⊕⊕ = 5)
+    // Repeated operators (⊕⊕⊕⊕⊕ = 5); alien digits such as ∀ are never
+    // counted, they read positionally below (∀∀ = 33)
     if let Some(first_char) = s.chars().next() {
-        if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸∀∃∄∅".contains(first_char) {
+        if "⊕⊗⊘⊚⊙⊞⊟⊠⨁⨂⨸".contains(first_char) {
             let count = s.chars().filter(|&c| c == first_char).count();
             if count == s.chars().count() {
-                return count as i64;
+                // A lone symbol is a literal (⊙ = 5), not a count of one
+                return Ok(if count == 1 {
+                    parse_operator_literal(s)
+                } else {
+                    count as i64
+                });
             }
         }
     }
 
-    // Chinese numbers
-    parse_chinese_number(s)
-}
-
-fn parse_chinese_number(s: &str) -> i64 {
-    let mut result = 0i64;
-    let mut current = 0i64;
-    let mut has_digit = false;
-
-    for ch in s.chars() {
-        match ch {
-            '零' | '〇' => {
-                has_digit = true;
-                current = 0;
-            }
-            '一' => {
-                has_digit = true;
-                current = 1;
-            }
-            '二' => {
-                has_digit = true;
-                current = 2;
-            }
-            '三' => {
-                has_digit = true;
-                current = 3;
-            }
-            '四' => {
-                has_digit = true;
-                current = 4;
-            }
-            '五' => {
-                has_digit = true;
-                current = 5;
-            }
-            '六' => {
-                has_digit = true;
-                current = 6;
-            }
-            '七' => {
-                has_digit = true;
-                current = 7;
-            }
-            '八' => {
-                has_digit = true;
-                current = 8;
-            }
-            '九' => {
-                has_digit = true;
-                current = 9;
-            }
-            '十' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result += current * 10;
-                current = 0;
-                has_digit = false;
-            }
-            '百' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result += current * 100;
-                current = 0;
-                has_digit = false;
-            }
-            '千' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result += current * 1000;
-                current = 0;
-                has_digit = false;
-            }
-            '万' => {
-                if !has_digit {
-                    current = 1;
-                }
-                result = (result + current) * 10000;
-                current = 0;
-                has_digit = false;
-            }
-            _ => {}
+    // Alien digit strings (℧℥ = 45)
+    if s.chars().all(|c| "∅∄∃∀℧℥℞℟℣℈".contains(c)) {
+        let mut result = 0i64;
+        for ch in s.chars() {
+            let digit = match ch {
+                '∅' => 0,
+                '∄' => 1,
+                '∃' => 2,
+                '∀' => 3,
+                '℧' => 4,
+                '℥' => 5,
+                '℞' => 6,
+                '℟' => 7,
+                '℣' => 8,
+                '℈' => 9,
+                _ => 0,
+            };
+            result = result
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(digit))
+                .ok_or_else(|| "number too large".to_string())?;
         }
+        return Ok(result);
     }
-    result + current
+
+    // Chinese numbers, including positional (一二三) and 又-joined forms
+    ling_number::chinese_to_number(s).ok_or_else(|| "number too large".to_string())
 }
 
 fn parse_operator_literal(op: &str) -> i64 {
@@ -347,6 +613,12 @@ fn parse_operator_literal(op: &str) -> i64 {
         "∃" => 2,
         "∄" => 1,
         "∅" => 0,
+        "℧" => 4,
+        "℥" => 5,
+        "℞" => 6,
+        "℟" => 7,
+        "℣" => 8,
+        "℈" => 9,
         _ => 0,
     }
 }
@@ -363,11 +635,23 @@ fn extract_string_content(s: &str) -> String {
     }
 }
 
-pub fn parse_value(pair: Pair<Rule>, env: &Environment) -> Value {
-    match pair.as_rule() {
-        Rule::NUMBER => Value::Number(parse_number(pair.as_str())),
+/// Read a literal (or a variable's current value) the way the parser does
+pub fn parse_value(pair: Pair<Rule>, env: &Environment) -> LingResult<Value> {
+    let too_large = || LingError::ParseError("number too large".to_string());
+    Ok(match pair.as_rule() {
+        Rule::NUMBER if pair.as_str().contains('点') => {
+            ling_number::chinese_to_float(pair.as_str())
+                .map(Value::Float)
+                .ok_or_else(too_large)?
+        }
+        Rule::NUMBER | Rule::OPERATOR_NUMBER => parse_number(pair.as_str())
+            .map(Value::Number)
+            .map_err(LingError::ParseError)?,
+        Rule::OPERATOR_LITERAL | Rule::OPERATOR_SYMBOL => {
+            Value::Number(parse_operator_literal(pair.as_str()))
+        }
         Rule::STRING => Value::String(extract_string_content(pair.as_str())),
         Rule::VAR_NAME => env.get(pair.as_str()).unwrap_or(Value::Number(0)),
         _ => Value::Number(0),
-    }
+    })
 }
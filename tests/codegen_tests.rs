@@ -0,0 +1,240 @@
+#[cfg(test)]
+mod tests {
+    use inkwell::context::Context;
+    use ling_lang::codegen::{Compiler, StringPart};
+    use ling_lang::parser;
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Debug info tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_debug_info_emits_source_locations() {
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "debug_module").with_debug_info(true);
+        compiler.set_source_file("tests/test_programs/hello.ling");
+
+        compiler.create_main_function();
+        compiler.set_source_location(1, 1);
+        compiler.store_string("x", "你好世界");
+        compiler.set_source_location(2, 1);
+        compiler.print_variable("x");
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("!dbg"));
+        assert!(ir.contains("DILocation(line: 2"));
+        assert!(ir.contains("hello.ling"));
+    }
+
+    #[test]
+    fn test_debug_info_disabled_by_default() {
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "plain_module");
+
+        compiler.create_main_function();
+        compiler.set_source_location(1, 1);
+        compiler.store_string("x", "你好世界");
+        compiler.print_variable("x");
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert!(!ir.contains("!dbg"));
+        assert!(!ir.contains("DILocation"));
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  String literal tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_repeated_string_literal_shares_global() {
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "intern_module");
+
+        compiler.create_main_function();
+        compiler.store_string("a", "hello");
+        compiler.store_string("b", "hello");
+        compiler.store_string("c", "world");
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert_eq!(ir.matches("c\"hello\\00\"").count(), 1);
+        assert_eq!(ir.matches("c\"world\\00\"").count(), 1);
+    }
+
+    #[test]
+    fn test_concat_interpolates_integer_variable() {
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "concat_module");
+
+        compiler.create_main_function();
+        compiler.store_integer("n", 42);
+        compiler.concat_strings(
+            "s",
+            vec![
+                StringPart::Literal("n = ".to_string()),
+                StringPart::Variable("n".to_string()),
+            ],
+        );
+        compiler.print_variable("s");
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("n = %lld"));
+        assert!(ir.contains("i64 42"));
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Control flow tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_if_else_on_integer_comparison() {
+        // ◬ ⊕⊕⊕ ▷ ⊕⊕ ◭ ⦃ ⟲ ⟦大⟧ ⋄ ⦄ ◮ ⦃ ⟲ ⟦小⟧ ⋄ ⦄
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "if_module");
+
+        compiler.create_main_function();
+        let left = compiler.const_integer(3);
+        let right = compiler.const_integer(2);
+        let cond = compiler.build_comparison("▷", left, right);
+
+        let blocks = compiler.build_if(cond);
+        compiler.print_literal("大");
+        compiler.build_else(&blocks);
+        compiler.print_literal("小");
+        compiler.end_if(blocks);
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("br i1"));
+        assert!(ir.contains("c\"\\E5\\A4\\A7\\00\"")); // 大
+        assert!(ir.contains("c\"\\E5\\B0\\8F\\00\"")); // 小
+    }
+
+    #[test]
+    fn test_print_comparison_as_chinese_boolean() {
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "bool_module");
+
+        compiler.create_main_function();
+        let left = compiler.const_integer(1);
+        let right = compiler.const_integer(2);
+        let cond = compiler.build_comparison("◁", left, right);
+        compiler.print_bool(cond);
+        compiler.finish_main();
+
+        // Constant operands fold the select, but both strings are emitted
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("c\"\\E7\\9C\\9F\\00\"")); // 真
+        assert!(ir.contains("c\"\\E5\\81\\87\\00\"")); // 假
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  AST compilation tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_compile_parsed_program() {
+        let statements = parser::parse_program(
+            "◈ 甲 ⇐ ⊕⊕ ⊞ ∀ ⋄ ◈ 名 ⇐ ⟦n=⟧ ⧺ 甲 ⋄ \
+             ◬ 甲 ▷ ∀ ◭ ⦃ ⟲ 名 ⋄ ⦄ ◮ ⦃ ⟲ ⟦small⟧ ⋄ ⦄ ⟴ 甲 ⋄",
+        )
+        .unwrap();
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "ast_module");
+
+        compiler.create_main_function();
+        assert!(compiler.compile_program(&statements).unwrap());
+
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("icmp sgt"));
+        assert!(ir.contains("br i1"));
+        assert!(ir.contains("n=%lld"));
+        assert!(ir.contains("c\"small\\00\""));
+        assert!(ir.contains("ret i32"));
+    }
+
+    #[test]
+    fn test_reassigned_concat_reuses_buffer() {
+        let statements =
+            parser::parse_program("◈ 甲 ⇐ ⊕ ⋄ ◈ 名 ⇐ ⟦a=⟧ ⧺ 甲 ⋄ ⟲ 名 ⋄ ◈ 名 ⇐ ⟦b=⟧ ⧺ 甲 ⋄ ⟲ 名 ⋄")
+                .unwrap();
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "concat_module");
+
+        compiler.create_main_function();
+        compiler.compile_program(&statements).unwrap();
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert_eq!(ir.matches("alloca i8, i64 1024").count(), 1);
+        assert!(ir.contains("a=%lld"));
+        assert!(ir.contains("b=%lld"));
+    }
+
+    #[test]
+    fn test_chinese_number_arithmetic_folds_to_constant() {
+        let statements = parser::parse_program("◈ x ⇐ 五 ⊞ 三 ⋄ ◈ y ⇐ 一二 ⊠ 二 ⋄ ⟲ x ⋄").unwrap();
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "chinese_module");
+
+        compiler.create_main_function();
+        compiler.compile_program(&statements).unwrap();
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("store i64 8"));
+        assert!(ir.contains("store i64 24"));
+        assert_eq!(compiler.parse_number("一二三"), Ok(123));
+        assert_eq!(
+            compiler.parse_number("九千万兆"),
+            Err("number too large".to_string())
+        );
+    }
+
+    #[test]
+    fn test_integer_variables_compile_to_native_arithmetic() {
+        let statements =
+            parser::parse_program("◈ x ⇐ ⊕⊕⊕ ⊞ ⊗⊗ ⋄ ⟲ x ⋄ ◈ y ⇐ x ⊟ ⊗⊗ ⊘ x ⊛ ⊗⊗ ⊠ x ⋄ ⟲ y ⋄")
+                .unwrap();
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "int_module");
+
+        compiler.create_main_function();
+        compiler.compile_program(&statements).unwrap();
+        compiler.finish_main();
+
+        let ir = compiler.llvm_ir();
+        assert!(ir.contains("alloca i64"));
+        assert!(ir.contains("store i64 5"));
+        assert!(ir.contains("c\"%lld\\0A\\00\""));
+        for instruction in ["sub i64", "sdiv i64", "srem i64", "mul i64"] {
+            assert!(ir.contains(instruction), "{}", instruction);
+        }
+    }
+
+    #[test]
+    fn test_variables_assigned_in_branches_are_read_after_merge() {
+        let statements = parser::parse_program(
+            "◈ x ⇐ ⊕ ⋄ ◈ s ⇐ ⟦a⟧ ⋄ \
+             ◬ x ▷ ⊕ ◭ ⦃ ◈ x ⇐ ⊗ ⋄ ◈ s ⇐ ⟦b⟧ ⋄ ⦄ ◮ ⦃ ◈ x ⇐ ⊕⊕⊕ ⋄ ◈ s ⇐ ⟦c=⟧ ⧺ x ⋄ ⦄ \
+             ⟲ x ⊞ ⊕ ⋄ ⟲ s ⋄",
+        )
+        .unwrap();
+        let context = Context::create();
+        let mut compiler = Compiler::new(&context, "branch_module");
+
+        compiler.create_main_function();
+        compiler.compile_program(&statements).unwrap();
+        compiler.finish_main();
+
+        assert_eq!(compiler.verify(), Ok(()));
+        let ir = compiler.llvm_ir();
+        assert_eq!(ir.matches("alloca i64").count(), 1);
+        assert_eq!(ir.matches("alloca ptr").count(), 1);
+        assert!(ir.contains("load i64, ptr %x"));
+        assert!(ir.contains("load ptr, ptr %s"));
+    }
+}
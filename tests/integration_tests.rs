@@ -10,12 +10,17 @@ mod tests {
 
     // ─── Helper: parse and interpret, returning the environment ───
     fn run_program(source: &str) -> Environment {
+        try_run_program(source).expect("Failed to execute")
+    }
+
+    // ─── Helper: parse and interpret, returning any runtime error ───
+    fn try_run_program(source: &str) -> LingResult<Environment> {
         let pairs = parse_program(source);
         let mut env = Environment::new();
         for pair in pairs {
-            executor::execute_program(pair, &mut env);
+            executor::execute_program(pair, &mut env)?;
         }
-        env
+        Ok(env)
     }
 
     // ═══════════════════════════════════════════════════════════════
@@ -92,6 +97,16 @@ mod tests {
         assert!(pairs.into_iter().next().is_some());
     }
 
+    #[test]
+    fn test_parse_nested_block_comment() {
+        let source = "◈ x ⇐ ⊕⊕⊕ ⋄ /* 外层 /* 内层 */ 仍是注释 */ ◈ y ⇐ x ⋄";
+        let pairs = parse_program(source);
+        assert!(pairs.into_iter().next().is_some());
+
+        let env = run_program(source);
+        assert_eq!(env.get("y"), Some(Value::Number(3)));
+    }
+
     #[test]
     fn test_parse_multiple_functions() {
         let source = "⟡ 加 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊞ b ⋄ ⦄ ⟡ 减 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊟ b ⋄ ⦄";
@@ -99,6 +114,90 @@ mod tests {
         assert!(pairs.into_iter().next().is_some());
     }
 
+    #[test]
+    fn test_parse_trailing_commas() {
+        let source = "⟡ 加 ⦃ a, b, ⦄ ⇒ ⦃ ⟴ a ⊞ b ⋄ ⦄";
+        let statements = parser::parse_program(source).unwrap();
+        assert!(matches!(
+            &statements[0],
+            parser::Statement::FuncDef { params, .. } if params == &["a", "b"]
+        ));
+
+        let env = run_program("◈ 数 ⇐ ⟬⊕, ⊗,⟭ ⋄ ◈ 和 ⇐ 求和(数,) ⋄");
+        assert_eq!(
+            env.get("数"),
+            Some(Value::Array(vec![Value::Number(1), Value::Number(2)]))
+        );
+        assert_eq!(env.get("和"), Some(Value::Number(3)));
+    }
+
+    #[test]
+    fn test_const_declaration_folds_to_literal() {
+        let statements = parser::parse_program("◆ 甲 ⇐ ⊕ ⊞ ⊗ ⋄ 常量 乙 ⇐ 甲 ⊞ ⊕ ⋄").unwrap();
+        assert!(matches!(
+            &statements[0],
+            parser::Statement::ConstDecl {
+                value: parser::Expression::Number(3),
+                literal: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &statements[1],
+            parser::Statement::ConstDecl { literal: false, .. }
+        ));
+
+        let env = run_program("◆ 甲 ⇐ ⊕ ⊞ ⊗ ⋄ ◈ 乙 ⇐ 甲 ⊞ ⊕ ⋄");
+        assert_eq!(env.get("甲"), Some(Value::Number(3)));
+        assert!(env.is_constant_literal("甲"));
+        assert!(!env.is_constant_literal("乙"));
+    }
+
+    #[test]
+    fn test_parsed_statements_keep_spans() {
+        let source = "◈ 甲 ⇐ ⊕ ⋄\n⟲ 甲 ⋄";
+        let statements = parser::parse_program_spanned(source).unwrap();
+        assert_eq!(statements.len(), 2);
+
+        let print = &statements[1];
+        assert!(matches!(print.statement, parser::Statement::Print { .. }));
+        assert_eq!(print.span.start, "◈ 甲 ⇐ ⊕ ⋄\n".len());
+        assert_eq!(&source[print.span.start..print.span.end], "⟲ 甲 ⋄");
+    }
+
+    #[test]
+    fn test_free_variables_skip_locals_and_params() {
+        let statements = parser::parse_program("⟡ 取 ⦃ n ⦄ ⇒ ⦃ ◈ 局 ⇐ n ⋄ ⟲ 局 ⊞ 外 ⋄ ⦄").unwrap();
+        assert_eq!(parser::free_variables(&statements), vec!["外".to_string()]);
+
+        let parser::Statement::FuncDef { body, .. } = &statements[0] else {
+            panic!("expected a function definition");
+        };
+        assert_eq!(
+            parser::free_variables(body),
+            vec!["n".to_string(), "外".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_function_attributes() {
+        let statements =
+            parser::parse_program("@记忆 @纯 ⟡ 平方 ⦃ n ⦄ ⇒ ⦃ ⟴ n ⊠ n ⋄ ⦄ ⟐ ⟡ 一 ⦃ ⦄ ⇒ ⦃ ⟴ ⊕ ⋄ ⦄")
+                .unwrap();
+        let parser::Statement::FuncDef { attributes, .. } = &statements[0] else {
+            panic!("expected a function definition");
+        };
+        assert_eq!(attributes, &["记忆", "纯"]);
+        assert!(matches!(
+            &statements[1],
+            parser::Statement::FuncDef { attributes, .. } if attributes == &["记忆"]
+        ));
+
+        // A labelled loop is not mistaken for an attributed statement
+        let env = run_program("◈ n ⇐ ∅ ⋄ @外 ⟳ n ◁ ∀ ⦃ n ⇐ n ⊞ ⊕ ⋄ ⦄");
+        assert_eq!(env.get("n"), Some(Value::Number(3)));
+    }
+
     #[test]
     fn test_parse_nested_if() {
         let source = "\
@@ -154,6 +253,366 @@ mod tests {
         assert_eq!(ling_number::chinese_to_number("三百"), Some(300));
     }
 
+    #[test]
+    fn test_chinese_number_zero_placeholders() {
+        assert_eq!(ling_number::chinese_to_number("一百零五"), Some(105));
+        assert_eq!(ling_number::chinese_to_number("一千零五"), Some(1005));
+        assert_eq!(ling_number::chinese_to_number("一万零一"), Some(10001));
+        assert_eq!(ling_number::chinese_to_number("一千零十"), Some(1010));
+
+        let env = run_program("◈ a ⇐ 一千零五 ⋄ ◈ b ⇐ 一万零一 ⋄ ◈ c ⇐ 一千零十 ⋄");
+        assert_eq!(env.get("a"), Some(Value::Number(1005)));
+        assert_eq!(env.get("b"), Some(Value::Number(10001)));
+        assert_eq!(env.get("c"), Some(Value::Number(1010)));
+    }
+
+    #[test]
+    fn test_chinese_number_yi_and_zhao_groupings() {
+        assert_eq!(ling_number::chinese_to_number("一亿"), Some(100_000_000));
+        assert_eq!(ling_number::chinese_to_number("十亿"), Some(1_000_000_000));
+        assert_eq!(
+            ling_number::chinese_to_number("三亿五千万"),
+            Some(350_000_000)
+        );
+        assert_eq!(
+            ling_number::chinese_to_number("二十亿三千万"),
+            Some(2_030_000_000)
+        );
+        assert_eq!(
+            ling_number::chinese_to_number("一亿零五万"),
+            Some(100_050_000)
+        );
+        assert_eq!(
+            ling_number::chinese_to_number("一万亿"),
+            Some(1_000_000_000_000)
+        );
+        assert_eq!(
+            ling_number::chinese_to_number("二兆三亿"),
+            Some(2_000_300_000_000)
+        );
+        assert_eq!(ling_number::chinese_to_number("一千万兆"), None);
+
+        let env = run_program("◈ a ⇐ 三亿五千万 ⋄");
+        assert_eq!(env.get("a"), Some(Value::Number(350_000_000)));
+    }
+
+    #[test]
+    fn test_chinese_number_negative_prefix() {
+        assert_eq!(ling_number::chinese_to_number("负五"), Some(-5));
+        assert_eq!(ling_number::chinese_to_number("负数一百零五"), Some(-105));
+        assert_eq!(ling_number::chinese_to_number("负"), None);
+        assert_eq!(ling_number::chinese_to_number("负数"), None);
+
+        let env = run_program("◈ t ⇐ 负十 ⋄ ◈ u ⇐ t ⊞ 负数三 ⋄ ◈ 负载 ⇐ ⊕ ⋄");
+        assert_eq!(env.get("t"), Some(Value::Number(-10)));
+        assert_eq!(env.get("u"), Some(Value::Number(-13)));
+        assert_eq!(env.get("负载"), Some(Value::Number(1)));
+    }
+
+    #[test]
+    fn test_number_to_chinese_round_trips() {
+        for (n, numeral) in [
+            (0, "零"),
+            (15, "十五"),
+            (105, "一百零五"),
+            (110, "一百一十"),
+            (1010, "一千零一十"),
+            (10_001, "一万零一"),
+            (100_001_000, "一亿零一千"),
+            (350_000_000, "三亿五千万"),
+            (-12, "负十二"),
+        ] {
+            assert_eq!(ling_number::number_to_chinese(n), numeral);
+        }
+
+        let samples = (-20_000..20_000)
+            .chain((0..i64::MAX).step_by(i64::MAX as usize / 997))
+            .chain([i64::MAX, i64::MIN + 1, 100_000_000_000_000]);
+        for n in samples {
+            let numeral = ling_number::number_to_chinese(n);
+            assert_eq!(
+                ling_number::chinese_to_number(&numeral),
+                Some(n),
+                "{}",
+                numeral
+            );
+        }
+    }
+
+    #[test]
+    fn test_chinese_numerals_read_the_same_everywhere() {
+        for numeral in ["一千零十", "三亿五千万", "负数一百零五", "二〇二六"] {
+            let expected = ling_number::chinese_to_number(numeral).unwrap();
+            let env = run_program(&format!("◈ 甲 ⇐ {} ⋄", numeral));
+            assert_eq!(env.get("甲"), Some(Value::Number(expected)), "{}", numeral);
+
+            let statements = parser::parse_program(&format!("◈ 甲 ⇐ {} ⋄", numeral)).unwrap();
+            assert!(
+                matches!(
+                    &statements[0],
+                    parser::Statement::VarDecl { value: parser::Expression::Number(n), .. }
+                        if *n == expected
+                ),
+                "{}",
+                numeral
+            );
+        }
+    }
+
+    #[test]
+    fn test_chinese_decimals_with_dian() {
+        assert_eq!(ling_number::chinese_to_float("三点一四"), Some(3.14));
+        assert_eq!(ling_number::chinese_to_float("零点五"), Some(0.5));
+        assert_eq!(ling_number::chinese_to_float("负十二点〇五"), Some(-12.05));
+        assert_eq!(ling_number::chinese_to_float("七"), Some(7.0));
+        assert_eq!(ling_number::chinese_to_float("三点"), None);
+        assert_eq!(ling_number::chinese_to_float("点五"), None);
+
+        let env = run_program("◈ 圆 ⇐ 三点一四 ⋄ ◈ 半 ⇐ 零点五 ⊞ 一 ⋄");
+        assert_eq!(env.get("圆"), Some(Value::Float(3.14)));
+        assert_eq!(env.get("半"), Some(Value::Float(1.5)));
+        assert!(parser::parse_program("◈ 圆 ⇐ 三点一四 ⋄").is_err());
+    }
+
+    #[test]
+    fn test_financial_chinese_numerals_match_simple_forms() {
+        let financial = |simple: &str| -> String {
+            simple
+                .chars()
+                .map(|c| match c {
+                    '一' => '壹',
+                    '二' => '贰',
+                    '三' => '叁',
+                    '四' => '肆',
+                    '五' => '伍',
+                    '六' => '陆',
+                    '七' => '柒',
+                    '八' => '捌',
+                    '九' => '玖',
+                    '十' => '拾',
+                    '百' => '佰',
+                    '千' => '仟',
+                    other => other,
+                })
+                .collect()
+        };
+        for n in 0..10_000 {
+            let simple = ling_number::number_to_chinese(n);
+            let numeral = financial(&simple);
+            assert_eq!(
+                ling_number::chinese_to_number(&numeral),
+                Some(n),
+                "{}",
+                numeral
+            );
+        }
+
+        assert_eq!(ling_number::chinese_to_number("壹佰贰拾叁"), Some(123));
+        let env = run_program("◈ 款 ⇐ 叁仟零伍拾 ⋄");
+        assert_eq!(env.get("款"), Some(Value::Number(3050)));
+    }
+
+    #[test]
+    fn test_chinese_number_digit_by_digit() {
+        assert_eq!(ling_number::chinese_to_number("一二三"), Some(123));
+        assert_eq!(ling_number::chinese_to_number("一 二 三"), Some(123));
+        assert_eq!(ling_number::chinese_to_number("二〇二六"), Some(2026));
+        assert_eq!(ling_number::chinese_to_number("二十三"), Some(23));
+    }
+
+    #[test]
+    fn test_chinese_number_you_connector() {
+        assert_eq!(ling_number::chinese_to_number("百又五"), Some(105));
+        assert_eq!(ling_number::chinese_to_number("一百又五"), Some(105));
+        assert_eq!(ling_number::chinese_to_number("一千又二十"), Some(1020));
+        assert_eq!(ling_number::chinese_to_number("一百零五"), Some(105));
+        assert_eq!(ling_number::chinese_to_number("又五"), None);
+        assert_eq!(ling_number::chinese_to_number("五又"), None);
+    }
+
+    #[test]
+    fn test_single_alien_digit_literals() {
+        for (i, digit) in "∅∄∃∀℧℥℞℟℣℈".chars().enumerate() {
+            let env = run_program(&format!("◈ x ⇐ {} ⋄", digit));
+            assert_eq!(
+                env.get("x"),
+                Some(Value::Number(i as i64)),
+                "digit {}",
+                digit
+            );
+        }
+
+        let env = run_program("◈ x ⇐ ℧℥ ⋄");
+        assert_eq!(env.get("x"), Some(Value::Number(45)));
+    }
+
+    #[test]
+    fn test_overlong_numerals_are_parse_errors() {
+        let too_large = Some(LingError::ParseError("number too large".to_string()));
+        let alien = "℈".repeat(25);
+        let ascii = "9".repeat(25);
+        for digits in [alien.as_str(), ascii.as_str(), "九千万兆"] {
+            let result = try_run_program(&format!("◈ x ⇐ {} ⋄", digits));
+            assert_eq!(result.err(), too_large, "{}", digits);
+
+            let parsed = parser::parse_program(&format!("◈ x ⇐ {} ⋄", digits));
+            assert_eq!(
+                parsed.err(),
+                Some("number too large".to_string()),
+                "{}",
+                digits
+            );
+        }
+
+        let env = run_program(&format!("◈ x ⇐ {} ⋄", "℈".repeat(18)));
+        assert_eq!(env.get("x"), Some(Value::Number(999_999_999_999_999_999)));
+
+        let statements = parser::parse_program("◈ x ⇐ ℧℥ ⋄").unwrap();
+        assert!(matches!(
+            &statements[..],
+            [parser::Statement::VarDecl {
+                value: parser::Expression::Number(45),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_parse_value_reads_literals_like_the_parser() {
+        let env = Environment::new();
+        let value = |rule, source| {
+            let pair = LingParser::parse(rule, source).unwrap().next().unwrap();
+            parser::parse_value(pair, &env)
+        };
+        assert_eq!(value(Rule::NUMBER, "℧℥"), Ok(Value::Number(45)));
+        assert_eq!(value(Rule::NUMBER, "⊕⊕⊕"), Ok(Value::Number(3)));
+        assert_eq!(value(Rule::NUMBER, "零点五"), Ok(Value::Float(0.5)));
+        assert_eq!(value(Rule::OPERATOR_LITERAL, "⊙"), Ok(Value::Number(5)));
+        assert_eq!(
+            value(Rule::NUMBER, "九千万兆"),
+            Err(LingError::ParseError("number too large".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_single_operator_is_literal_repeated_is_count() {
+        // Alien digits read positionally whether or not they repeat
+        let env = run_program("◈ 甲 ⇐ ⊙ ⋄ ◈ 乙 ⇐ ⊙⊙ ⋄ ◈ 丙 ⇐ ∀∀ ⋄ ◈ 丁 ⇐ ℧℧ ⋄ ◈ 戊 ⇐ ∄∅ ⋄");
+        assert_eq!(env.get("甲"), Some(Value::Number(5)));
+        assert_eq!(env.get("乙"), Some(Value::Number(2)));
+        assert_eq!(env.get("丙"), Some(Value::Number(33)));
+        assert_eq!(env.get("丁"), Some(Value::Number(44)));
+        assert_eq!(env.get("戊"), Some(Value::Number(10)));
+
+        let statements = parser::parse_program("◈ 甲 ⇐ ⊙ ⋄ ◈ 乙 ⇐ ⊙⊙ ⋄ ◈ 丙 ⇐ ∀∀ ⋄").unwrap();
+        assert!(matches!(
+            &statements[..],
+            [
+                parser::Statement::VarDecl {
+                    value: parser::Expression::Number(5),
+                    ..
+                },
+                parser::Statement::VarDecl {
+                    value: parser::Expression::Number(2),
+                    ..
+                },
+                parser::Statement::VarDecl {
+                    value: parser::Expression::Number(33),
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_chinese_ordinals() {
+        assert_eq!(ling_number::parse_ordinal("第一"), Some(1));
+        assert_eq!(ling_number::parse_ordinal("第三"), Some(3));
+        assert_eq!(ling_number::parse_ordinal("第十二"), Some(12));
+        assert_eq!(ling_number::parse_ordinal("第"), None);
+        assert_eq!(ling_number::parse_ordinal("三"), None);
+    }
+
+    #[test]
+    fn test_ordinal_index_is_zero_based() {
+        let env = run_program("◈ 数组 ⇐ [⊕⊕, ⊕⊕⊕, ⊕⊕⊕⊕] ⋄ ◈ 首 ⇐ 数组[第一] ⋄ ◈ 末 ⇐ 数组[第三] ⋄");
+        assert_eq!(env.get("首"), Some(Value::Number(2)));
+        assert_eq!(env.get("末"), Some(Value::Number(4)));
+    }
+
+    #[test]
+    fn test_assign_array_element() {
+        let env = run_program("◈ 数组 ⇐ [⊕, ⊗, ∀] ⋄ 数组[⊕] ⇐ ℈ ⋄ 数组[第三] ⇐ ∅ ⋄");
+        assert_eq!(
+            env.get("数组"),
+            Some(Value::Array(vec![
+                Value::Number(1),
+                Value::Number(9),
+                Value::Number(0),
+            ]))
+        );
+
+        let result = try_run_program("◈ 数组 ⇐ [⊕] ⋄ 数组[⊗] ⇐ ℈ ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::RuntimeError(
+                "Index 2 out of bounds for array of length 1".to_string()
+            ))
+        );
+
+        let result = try_run_program("◆ 常 ⇐ [⊕] ⋄ 常[∅] ⇐ ℈ ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::ImmutableAssignment("常".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_assign_map_entry() {
+        let mut env = Environment::new();
+        env.set(
+            "表".to_string(),
+            Value::Map([("键".to_string(), Value::Number(1))].into_iter().collect()),
+        );
+        for pair in parse_program("表[⟦键⟧] ⇐ ⊗ ⋄ 表[⟦新⟧] ⇐ ∀ ⋄ ◈ 值 ⇐ 表[⟦键⟧] ⋄")
+        {
+            executor::execute_program(pair, &mut env).unwrap();
+        }
+        assert_eq!(
+            env.get("表"),
+            Some(Value::Map(
+                [
+                    ("键".to_string(), Value::Number(2)),
+                    ("新".to_string(), Value::Number(3)),
+                ]
+                .into_iter()
+                .collect()
+            ))
+        );
+        assert_eq!(env.get("值"), Some(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_member_access_reads_string_key() {
+        let env = run_program(
+            "◈ config ⇐ 建映射(⟬⟬⟦port⟧, ⊗⟭, ⟬⟦服务⟧, 建映射(⟬⟬⟦名⟧, ⟦灵⟧⟭⟭)⟭⟭) ⋄ \
+             ◈ 端口 ⇐ config.port ⋄ ◈ 名 ⇐ config.服务.名 ⋄ ◈ 同 ⇐ config.port ⊙ config[⟦port⟧] ⋄",
+        );
+        assert_eq!(env.get("端口"), Some(Value::Number(2)));
+        assert_eq!(env.get("名"), Some(Value::String("灵".to_string())));
+        assert_eq!(env.get("同"), Some(Value::Boolean(true)));
+
+        let result = try_run_program("◈ config ⇐ 建映射(⟬⟭) ⋄ ◈ 端口 ⇐ config.port ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::RuntimeError("no such key".to_string()))
+        );
+        assert!(matches!(
+            try_run_program("◈ 数 ⇐ ⟬⊕⟭ ⋄ ◈ 端口 ⇐ 数.port ⋄"),
+            Err(LingError::TypeError { .. })
+        ));
+    }
+
     // ═══════════════════════════════════════════════════════════════
     //  Environment tests
     // ═══════════════════════════════════════════════════════════════
@@ -184,6 +643,49 @@ mod tests {
         assert_eq!(env.get("x"), Some(Value::Number(1)));
     }
 
+    #[test]
+    fn test_env_function_scope_hides_caller_locals() {
+        let mut env = Environment::new();
+        env.set("x".to_string(), Value::Number(1));
+
+        // Caller's local shadows the global
+        env.push_scope();
+        env.set("x".to_string(), Value::Number(2));
+        env.set("y".to_string(), Value::Number(3));
+
+        // The callee sees the global x and its closure, not the caller's locals
+        env.push_function_scope(vec![("z".to_string(), Value::Number(4))]);
+        assert_eq!(env.get("x"), Some(Value::Number(1)));
+        assert_eq!(env.get("y"), None);
+        assert_eq!(env.get("z"), Some(Value::Number(4)));
+        assert!(env.update("y", Value::Number(0)).is_err());
+
+        env.pop_scope();
+        assert_eq!(env.get("x"), Some(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_env_diff_between_snapshots() {
+        let mut interp = Interpreter::new();
+        interp.load("◈ x ⇐ ⊕ ⋄ ◈ x ⇐ ⊗ ⋄ ◈ y ⇐ ∀ ⋄").unwrap();
+        interp.step().unwrap();
+        let before = interp.env().clone();
+
+        interp.step().unwrap();
+        interp.step().unwrap();
+        assert_eq!(
+            before.diff(interp.env()),
+            vec![
+                environment::VarChange::Changed(
+                    "x".to_string(),
+                    Value::Number(1),
+                    Value::Number(2)
+                ),
+                environment::VarChange::Added("y".to_string(), Value::Number(3)),
+            ]
+        );
+    }
+
     #[test]
     fn test_env_immutable_variable() {
         let mut env = Environment::new();
@@ -194,6 +696,27 @@ mod tests {
         assert_eq!(env.get("pi"), Some(Value::Number(314)));
     }
 
+    #[test]
+    fn test_env_inner_variable_shadows_outer_const() {
+        let mut env = Environment::new();
+        env.set_const("常".to_string(), Value::Number(1));
+
+        env.push_scope();
+        env.set("常".to_string(), Value::Number(2));
+        assert_eq!(env.update("常", Value::Number(3)), Ok(()));
+        assert_eq!(env.get("常"), Some(Value::Number(3)));
+        env.set_const("常".to_string(), Value::Number(4));
+        assert!(env.update("常", Value::Number(5)).is_err());
+        env.pop_scope();
+
+        assert_eq!(env.get("常"), Some(Value::Number(1)));
+        assert_eq!(env.is_mutable("常"), Some(false));
+        assert!(env.update("常", Value::Number(6)).is_err());
+
+        let env = run_program("◆ 常 ⇐ ⊕ ⋄ ◬ ⊕ ◭ ⦃ ◈ 常 ⇐ ⊗ ⋄ 常 ⇐ ∀ ⋄ ◈ 内 ⇐ 常 ⋄ ⦄");
+        assert_eq!(env.get("常"), Some(Value::Number(1)));
+    }
+
     #[test]
     fn test_env_mutable_update() {
         let mut env = Environment::new();
@@ -237,6 +760,34 @@ mod tests {
         assert!(!Value::Void.is_truthy());
     }
 
+    #[test]
+    fn test_empty_collections_are_falsy() {
+        assert!(!Value::Array(vec![]).is_truthy());
+        assert!(Value::Array(vec![Value::Number(0)]).is_truthy());
+        assert!(!Value::Map(Default::default()).is_truthy());
+        assert!(Value::Map([("键".to_string(), Value::Void)].into_iter().collect()).is_truthy());
+        assert!(
+            !Value::Range {
+                start: 2,
+                end: 1,
+                step: 1
+            }
+            .is_truthy()
+        );
+        assert!(
+            Value::Range {
+                start: 1,
+                end: 1,
+                step: 1
+            }
+            .is_truthy()
+        );
+
+        let mut interp = Interpreter::new();
+        interp.run("⟳ ⟬⟭ ⦃ ⟲ ⟦不会输出⟧ ⋄ ⦄").unwrap();
+        assert_eq!(interp.output(), "");
+    }
+
     #[test]
     fn test_value_to_number() {
         assert_eq!(Value::Number(42).to_number(), Some(42));
@@ -246,6 +797,68 @@ mod tests {
         assert_eq!(Value::Void.to_number(), None);
     }
 
+    #[test]
+    fn test_value_coerce_to_number_table() {
+        let type_error = |found: &str| {
+            Err(LingError::TypeError {
+                expected: "number".to_string(),
+                found: found.to_string(),
+            })
+        };
+        let cases = [
+            (Value::Number(-7), Ok(-7), Ok(-7)),
+            (Value::Boolean(true), Ok(1), Ok(1)),
+            (Value::Boolean(false), Ok(0), Ok(0)),
+            (Value::String("42".to_string()), Ok(42), Ok(42)),
+            (
+                Value::String("四十二".to_string()),
+                Ok(0),
+                type_error("string"),
+            ),
+            (Value::Void, Ok(0), type_error("void")),
+            (Value::Char('a'), type_error("char"), type_error("char")),
+            (
+                Value::Array(vec![]),
+                type_error("array"),
+                type_error("array"),
+            ),
+            (
+                Value::Map(Default::default()),
+                type_error("map"),
+                type_error("map"),
+            ),
+            (
+                Value::Range {
+                    start: 1,
+                    end: 3,
+                    step: 1,
+                },
+                type_error("range"),
+                type_error("range"),
+            ),
+        ];
+        for (value, lenient, strict) in cases {
+            assert_eq!(
+                value.coerce_to_number(false),
+                lenient,
+                "lenient {:?}",
+                value
+            );
+            assert_eq!(value.coerce_to_number(true), strict, "strict {:?}", value);
+        }
+
+        let config = LingConfig {
+            strict_mode: true,
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        assert!(interp.run("◈ x ⇐ ⟦abc⟧ ⊞ ⊕ ⋄").is_err());
+        assert_eq!(
+            run_program("◈ x ⇐ ⟦abc⟧ ⊞ ⊕ ⋄").get("x"),
+            Some(Value::Number(1))
+        );
+    }
+
     #[test]
     fn test_value_display() {
         assert_eq!(format!("{}", Value::Number(42)), "42");
@@ -254,6 +867,47 @@ mod tests {
         assert_eq!(format!("{}", Value::Void), "");
     }
 
+    #[test]
+    fn test_value_display_truncates_deep_nesting() {
+        let mut nested = Value::Number(1);
+        for _ in 0..3 {
+            nested = Value::Array(vec![nested]);
+        }
+        assert_eq!(nested.to_string(), "[[[1]]]");
+        assert_eq!(nested.to_string_depth(2), "[[[...]]]");
+        assert_eq!(nested.to_string_depth(0), "[...]");
+
+        for _ in 0..100 {
+            nested = Value::Array(vec![nested]);
+        }
+        let depth = value::DISPLAY_DEPTH;
+        let expected = format!("{}[...]{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(nested.to_string(), expected);
+    }
+
+    #[test]
+    fn test_value_display_width() {
+        assert_eq!(Value::from("ab").display_width(), 2);
+        assert_eq!(Value::from("你好").display_width(), 4);
+        assert_eq!(Value::from("a你").display_width(), 3);
+        assert_eq!(Value::Number(123).display_width(), 3);
+    }
+
+    #[test]
+    fn test_value_signum_and_cmp() {
+        assert_eq!(Value::Number(-7).signum(), Ok(Value::Number(-1)));
+        assert_eq!(Value::Number(0).signum(), Ok(Value::Number(0)));
+        assert!(Value::from("a").signum().is_err());
+
+        assert_eq!(Value::from("a").cmp_value(&Value::from("b")), Ok(-1));
+        assert_eq!(Value::from("b").cmp_value(&Value::from("a")), Ok(1));
+        assert_eq!(Value::from("a").cmp_value(&Value::from("a")), Ok(0));
+        assert!(matches!(
+            Value::from("a").cmp_value(&Value::Number(1)),
+            Err(LingError::InvalidOperation { .. })
+        ));
+    }
+
     #[test]
     fn test_value_from_conversions() {
         let n: Value = 42.into();
@@ -266,20 +920,84 @@ mod tests {
         assert_eq!(b, Value::Boolean(true));
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_value_json_round_trip() {
+        let values = vec![
+            Value::Number(42),
+            Value::Float(1.5),
+            Value::Float(2.0),
+            Value::Float(1e-11),
+            Value::String("你好".to_string()),
+            Value::Boolean(true),
+            Value::Array(vec![Value::Number(1), Value::String("二".to_string())]),
+            Value::Map([("键".to_string(), Value::Number(7))].into_iter().collect()),
+            Value::Void,
+        ];
+        for value in values {
+            let json: serde_json::Value = value.clone().into();
+            assert_eq!(Value::from_json_value(json), Ok(value.clone()));
+
+            // Exported JSON text reads back as the same value too
+            let text: serde_json::Value = serde_json::from_str(&value.to_json_pretty(0)).unwrap();
+            assert_eq!(Value::from_json_value(text), Ok(value));
+        }
+
+        let func = Value::Function(value::FunctionValue::new(
+            "主".to_string(),
+            vec![],
+            value::FunctionBody::parse("").unwrap(),
+            vec![],
+        ));
+        assert_eq!(func.to_json_pretty(0), "\"<function 主()>\"");
+        let json: serde_json::Value = func.into();
+        assert_eq!(json, serde_json::json!("<function 主()>"));
+        assert_eq!(
+            Value::from_json_value(serde_json::json!(1.5)),
+            Ok(Value::Float(1.5))
+        );
+        assert_eq!(Value::Float(1e-11).to_string(), "0.00000000001");
+    }
+
+    #[test]
+    fn test_pretty_json_indents_nested_map() {
+        let env = run_program(
+            "◈ 表 ⇐ 建映射(⟬⟬⟦名⟧, ⟦\"灵\"⟧⟭, ⟬⟦列⟧, ⟬⊕, ⊗⟭⟭, ⟬⟦空⟧, 建映射(⟬⟭)⟭⟭) ⋄ ◈ 文 ⇐ 美化JSON(表) ⋄",
+        );
+        let expected =
+            "{\n  \"列\": [\n    1,\n    2\n  ],\n  \"名\": \"\\\"灵\\\"\",\n  \"空\": {}\n}";
+        assert_eq!(env.get("文"), Some(Value::String(expected.to_string())));
+
+        let adder = Value::Function(value::FunctionValue::new(
+            "加".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+            value::FunctionBody::parse("").unwrap(),
+            vec![],
+        ));
+        assert_eq!(
+            Value::Array(vec![adder]).to_json_pretty(0),
+            "[\n\"<function 加(a, b)>\"\n]"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_environment_to_json_pretty() {
+        let env = run_program("◈ 甲 ⇐ ⟬⊕⟭ ⋄ ◈ 乙 ⇐ ⟦字⟧ ⋄");
+        assert_eq!(
+            env.to_json_pretty(4),
+            Ok("{\n    \"乙\": \"字\",\n    \"甲\": [\n        1\n    ]\n}".to_string())
+        );
+    }
+
     // ═══════════════════════════════════════════════════════════════
     //  Utility function tests
     // ═══════════════════════════════════════════════════════════════
 
     #[test]
     fn test_values_equal() {
-        assert!(utils::values_equal(
-            &Value::Number(1),
-            &Value::Number(1)
-        ));
-        assert!(!utils::values_equal(
-            &Value::Number(1),
-            &Value::Number(2)
-        ));
+        assert!(utils::values_equal(&Value::Number(1), &Value::Number(1)));
+        assert!(!utils::values_equal(&Value::Number(1), &Value::Number(2)));
         assert!(utils::values_equal(
             &Value::String("a".into()),
             &Value::String("a".into())
@@ -290,6 +1008,41 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_float_equality_honours_epsilon() {
+        let (sum, third) = (Value::Float(0.1 + 0.2), Value::Float(0.3));
+        assert!(utils::values_equal(&sum, &third));
+        assert!(utils::values_equal_within(&sum, &third, 1e-9));
+        assert!(!utils::values_equal_within(&sum, &third, 0.0));
+
+        let program = "◈ 等 ⇐ 零点一 ⊞ 零点二 ≡ 零点三 ⋄ \
+                       ◈ 数 ⇐ 去重(⟬零点一 ⊞ 零点二, 零点三⟭) ⋄ \
+                       ◈ 次 ⇐ 计数(⟬零点三, 零点一 ⊞ 零点二⟭, 零点三) ⋄";
+        let env = run_program(program);
+        assert_eq!(env.get("等"), Some(Value::Boolean(true)));
+        assert_eq!(
+            env.get("数"),
+            Some(Value::Array(vec![Value::Float(0.1 + 0.2)]))
+        );
+        assert_eq!(env.get("次"), Some(Value::Number(2)));
+
+        let config = LingConfig {
+            float_epsilon: 0.0,
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        interp.run(program).unwrap();
+        assert_eq!(interp.env().get("等"), Some(Value::Boolean(false)));
+        assert_eq!(
+            interp.env().get("数"),
+            Some(Value::Array(vec![
+                Value::Float(0.1 + 0.2),
+                Value::Float(0.3)
+            ]))
+        );
+        assert_eq!(interp.env().get("次"), Some(Value::Number(1)));
+    }
+
     #[test]
     fn test_normalize_operator() {
         assert_eq!(utils::normalize_operator("⊕"), "+");
@@ -330,4 +1083,1436 @@ mod tests {
         assert!(msg.contains("number"));
         assert!(msg.contains("string"));
     }
+
+    #[test]
+    fn test_eval_returns_last_statement_value() {
+        assert_eq!(eval("◈ 甲 ⇐ ∀ ⋄ 甲 ⊠ 甲 ⋄"), Ok(Value::Number(9)));
+        assert_eq!(eval("⟦你⟧ ⧺ ⟦好⟧"), Ok(Value::String("你好".to_string())));
+        assert_eq!(eval(""), Ok(Value::Void));
+        assert_eq!(
+            eval("◈ 甲 ⇐ ∀ ⋄ 甲 ⊠ 甲 ⋄ ◈ 乙 ⇐ 甲 ⋄ ⟲ 乙 ⋄"),
+            Ok(Value::Number(9))
+        );
+        assert_eq!(eval("◈ 甲 ⇐ ∀ ⋄"), Ok(Value::Void));
+
+        let mut env = Environment::new();
+        let pair = LingParser::parse(Rule::PROGRAM, "⊕ ⊞ ⊕ ⋄ ◈ 甲 ⇐ ∀ ⋄")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(
+            executor::execute_program(pair, &mut env),
+            Ok(Value::Number(2))
+        );
+        assert!(matches!(eval("◈ ⇐ ⋄"), Err(LingError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_evaluation_errors_are_returned_not_panicked() {
+        assert_eq!(
+            try_run_program("◈ y ⇐ x ⊞ ⊕ ⋄").err(),
+            Some(LingError::UndefinedVariable("x".to_string()))
+        );
+        assert_eq!(
+            try_run_program("◈ y ⇐ ⊕ ⊘ ∅ ⋄").err(),
+            Some(LingError::DivisionByZero)
+        );
+        assert_eq!(
+            try_run_program("◈ y ⇐ ⟬⊕⟭ ⊞ ⊕ ⋄").err(),
+            Some(LingError::InvalidOperation {
+                op: "⊞".to_string(),
+                left: "array".to_string(),
+                right: "number".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors_report_line_and_column() {
+        let mut interp = Interpreter::new();
+        let err = interp.run("◈ 甲 ⇐ ⊕ ⋄\n◈ 乙 ⇐ 甲 ⊞ 未定义 ⋄").unwrap_err();
+        assert_eq!(interp.error_location(), Some((2, 11)));
+        assert_eq!(
+            interp.locate(err).to_string(),
+            "Undefined variable: 未定义 at line 2, col 11"
+        );
+
+        // Failures inside a function point at the call
+        let err = interp
+            .run("⟡ 除 ⦃ n ⦄ ⇒ ⦃ ⟴ ⊕ ⊘ n ⋄ ⦄\n⟲ ⊕ ⋄\n  ⟲ 除(∅) ⋄")
+            .unwrap_err();
+        assert_eq!(err, LingError::DivisionByZero);
+        assert_eq!(interp.error_location(), Some((3, 5)));
+
+        assert!(interp.run("◈ 甲 ⇐ ⊕ ⋄\n◈ ⇐ ⋄").is_err());
+        assert_eq!(interp.error_location().map(|(line, _)| line), Some(2));
+
+        interp.run("⟲ ⊕ ⋄").unwrap();
+        assert_eq!(interp.error_location(), None);
+        let located = Located::from(LingError::DivisionByZero);
+        assert_eq!(located.to_string(), "Division by zero");
+    }
+
+    #[test]
+    fn test_error_categories() {
+        let err = try_run_program("◈ x ⇐ ⊕ ⊘ ∅ ⋄").unwrap_err();
+        assert_eq!(err.category(), ErrorCategory::Runtime);
+
+        assert_eq!(
+            LingError::ParseError("bad".to_string()).category(),
+            ErrorCategory::Syntax
+        );
+        assert_eq!(LingError::DivisionByZero.category(), ErrorCategory::Runtime);
+        assert_eq!(
+            LingError::UndefinedVariable("x".to_string()).category(),
+            ErrorCategory::Runtime
+        );
+        assert_eq!(
+            LingError::TypeError {
+                expected: "number".to_string(),
+                found: "string".to_string(),
+            }
+            .category(),
+            ErrorCategory::Type
+        );
+        assert_eq!(
+            LingError::CompilationError("x".to_string()).category(),
+            ErrorCategory::Compile
+        );
+        assert_eq!(
+            LingError::IOError("x".to_string()).category(),
+            ErrorCategory::Io
+        );
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Interpreter tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_interpreter_step_through_program() {
+        let mut interp = Interpreter::new();
+        interp
+            .load("◈ x ⇐ ⊕⊕⊕ ⋄ ◈ y ⇐ x ⊞ ⊗⊗ ⋄ x ⊠ y ⋄")
+            .expect("Failed to load");
+
+        assert_eq!(interp.step(), Ok(Some(Value::Void)));
+        assert_eq!(interp.env().get("x"), Some(Value::Number(3)));
+        assert_eq!(interp.env().get("y"), None);
+
+        assert_eq!(interp.step(), Ok(Some(Value::Void)));
+        assert_eq!(interp.env().get("y"), Some(Value::Number(5)));
+
+        assert_eq!(interp.step(), Ok(Some(Value::Number(15))));
+        assert!(interp.is_finished());
+        assert_eq!(interp.step(), Ok(None));
+    }
+
+    #[test]
+    fn test_coverage_reports_untaken_else_branch() {
+        let source = "◈ x ⇐ ⊕ ⋄ ◬ x ▷ ∅ ◭ ⦃ ⟲ ⟦是⟧ ⋄ ⦄ ◮ ⦃ ⟲ ⟦否⟧ ⋄ ⦄";
+        let mut interp = Interpreter::new();
+        interp.run(source).unwrap();
+
+        let coverage: Vec<_> = interp
+            .coverage()
+            .into_iter()
+            .map(|(span, run)| (source[span.start..span.end].trim(), run))
+            .collect();
+        assert_eq!(
+            coverage,
+            vec![
+                ("◈ x ⇐ ⊕ ⋄", true),
+                ("◬ x ▷ ∅ ◭ ⦃ ⟲ ⟦是⟧ ⋄ ⦄ ◮ ⦃ ⟲ ⟦否⟧ ⋄ ⦄", true),
+                ("⟲ ⟦是⟧ ⋄", true),
+                ("⟲ ⟦否⟧ ⋄", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_feed_keeps_variables_between_lines() {
+        let mut interp = Interpreter::new();
+        assert_eq!(interp.feed("◈ x ⇐ ⊕⊕⊕ ⋄"), Ok(Value::Void));
+        interp.feed("⟲ x ⋄").unwrap();
+        assert_eq!(interp.feed("x ⊞ ⊕"), Ok(Value::Number(4)));
+        assert_eq!(interp.output(), "3\n");
+
+        assert_eq!(
+            interp.feed("⟲ x ⋄ ⟲ x ⋄"),
+            Err(LingError::ParseError(
+                "Expected a single statement, found 2".to_string()
+            ))
+        );
+        assert_eq!(interp.output(), "3\n");
+
+        interp.reset();
+        assert_eq!(
+            interp.feed("⟲ x ⋄"),
+            Err(LingError::UndefinedVariable("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_interpreter_output_limit() {
+        let config = LingConfig {
+            max_output_bytes: Some(16),
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        interp.load("⟳ ⊕ ⦃ ⟲ ⟦你好⟧ ⋄ ⦄").unwrap();
+
+        assert_eq!(
+            interp.step(),
+            Err(LingError::RuntimeError("output limit exceeded".to_string()))
+        );
+        assert_eq!(interp.output(), "你好\n你好\n");
+    }
+
+    #[test]
+    fn test_modulo_operator() {
+        let env = run_program("◈ r ⇐ ⊕⊕⊕⊕⊕ ⊛ ⊗⊗ ⋄ ◈ s ⇐ ℈ ⊞ ℞ % ℧ ⊠ ⊗ ⋄");
+        assert_eq!(env.get("r"), Some(Value::Number(1)));
+        assert_eq!(env.get("s"), Some(Value::Number(13)));
+
+        assert_eq!(
+            try_run_program("◈ r ⇐ ℥ ⊛ ∅ ⋄").err(),
+            Some(LingError::DivisionByZero)
+        );
+        assert_eq!(utils::normalize_operator("⊛"), "%");
+    }
+
+    #[test]
+    fn test_integer_overflow_is_a_runtime_error() {
+        let overflow = Some(LingError::RuntimeError("integer overflow".to_string()));
+        let min = "◈ 甲 ⇐ ∅ ⊟ 9223372036854775807 ⊟ ⊕ ⋄";
+        for op in ["⊘", "⊛", "⊠"] {
+            let result = try_run_program(&format!("{} ◈ 乙 ⇐ 甲 {} 负一 ⋄", min, op));
+            assert_eq!(result.err(), overflow, "{}", op);
+        }
+        for source in [
+            "◈ 甲 ⇐ 9223372036854775807 ⋄ ◈ 乙 ⇐ 甲 ⊞ ⊕ ⋄",
+            "◈ 甲 ⇐ ∅ ⊟ 9223372036854775807 ⋄ ◈ 乙 ⇐ 甲 ⊟ ⊗ ⋄",
+        ] {
+            assert_eq!(try_run_program(source).err(), overflow, "{}", source);
+        }
+
+        let env = run_program(&format!("{} ◈ 乙 ⇐ 甲 ⊘ ⊕ ⋄", min));
+        assert_eq!(env.get("乙"), Some(Value::Number(i64::MIN)));
+    }
+
+    #[test]
+    fn test_float_division_flag_promotes_inexact_quotients() {
+        assert_eq!(
+            run_program("◈ 甲 ⇐ ℥ ⊘ ⊗ ⋄").get("甲"),
+            Some(Value::Number(2))
+        );
+
+        let config = LingConfig {
+            float_division: true,
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        interp
+            .run("◈ 甲 ⇐ ℥ ⊘ ⊗ ⋄ ⟲ 甲 ⋄ ⟲ 甲 ⊞ ⊕ ⋄ ⟲ ℣ ⊘ ⊗ ⋄")
+            .unwrap();
+        assert_eq!(interp.output(), "2.5\n3.5\n4\n");
+
+        assert_eq!(Value::from(3.0).to_string(), "3.0");
+        assert_eq!(Value::from(2.5).type_name(), "float");
+        assert_eq!(Value::from(2.5).to_number(), Some(2));
+        assert_eq!(Value::Number(2).to_float(), Some(2.0));
+        assert!(!Value::from(0.0).is_truthy());
+        assert!(!Value::from(f64::NAN).is_truthy());
+        assert!(Value::from(0.5).is_truthy());
+    }
+
+    #[test]
+    fn test_print_output_is_captured() {
+        let source = "⟲ ⟦你好⟧ ⋄ ⟲ ⟦世界⟧ ⋄";
+        let mut interp = Interpreter::new();
+        interp.run(source).unwrap();
+        assert_eq!(interp.output(), "你好\n世界\n");
+
+        let mut out = Vec::new();
+        let mut env = Environment::new();
+        let pair = LingParser::parse(Rule::PROGRAM, source)
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut ctx = executor::ExecContext::new(&mut out, LingConfig::default());
+        executor::execute(pair, &mut env, &mut ctx).unwrap();
+        assert_eq!(out, "你好\n世界\n".as_bytes());
+    }
+
+    #[test]
+    fn test_error_print_goes_to_separate_sink() {
+        let mut interp = Interpreter::new();
+        interp
+            .run("⟲ ⟦结果⟧ ⋄ ⚠ ⟦警告⟧, ⊕⊕ ⋄ 错误输出 ⟦完成⟧ ⋄ ⟲ ⊕ ⋄")
+            .unwrap();
+        assert_eq!(interp.output(), "结果\n1\n");
+        assert_eq!(interp.error_output(), "警告 2\n完成\n");
+
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut env = Environment::new();
+        let pair = LingParser::parse(Rule::PROGRAM, "⚠ ⟦日志⟧ ⋄")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut ctx =
+            executor::ExecContext::new(&mut out, LingConfig::default()).with_err_out(&mut err);
+        executor::execute(pair, &mut env, &mut ctx).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(err, "日志\n".as_bytes());
+    }
+
+    #[test]
+    fn test_dry_run_reports_effects_without_applying_them() {
+        let interp = Interpreter::new();
+        let effects = interp
+            .dry_run("◈ x ⇐ ⊕⊕ ⋄ x ⇐ x ⊞ ⊕ ⋄ ⟲ x ⋄ ⚠ ⟦完成⟧ ⋄")
+            .unwrap();
+        assert_eq!(
+            effects,
+            vec![
+                executor::Effect::Write {
+                    name: "x".to_string(),
+                    value: Value::Number(2),
+                },
+                executor::Effect::Write {
+                    name: "x".to_string(),
+                    value: Value::Number(3),
+                },
+                executor::Effect::Print("3".to_string()),
+                executor::Effect::ErrorPrint("完成".to_string()),
+            ]
+        );
+        assert_eq!(interp.env().get("x"), None);
+        assert_eq!(interp.output(), "");
+    }
+
+    #[test]
+    fn test_interpreter_run_keeps_state_between_calls() {
+        let mut interp = Interpreter::new();
+        interp.run("◈ x ⇐ ⊕⊕ ⋄").unwrap();
+        assert_eq!(interp.run("x ⊞ ⊕"), Ok(Value::Number(3)));
+
+        interp.reset();
+        assert_eq!(interp.env().get("x"), None);
+        assert_eq!(
+            interp.run("x"),
+            Err(LingError::UndefinedVariable("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reactive_recomputes_when_input_changes() {
+        let mut interp = Interpreter::new();
+        interp.run("◈ 价 ⇐ ∃ ⋄ ◈ 量 ⇐ ∀ ⋄").unwrap();
+        interp.define_reactive("总", "价 ⊠ 量").unwrap();
+        interp.define_reactive("税", "总 ⊘ ∃").unwrap();
+        assert_eq!(interp.env().get("总"), Some(Value::Number(6)));
+        assert_eq!(interp.env().get("税"), Some(Value::Number(3)));
+
+        interp.run("价 ⇐ ℥ ⋄").unwrap();
+        assert_eq!(interp.env().get("总"), Some(Value::Number(15)));
+        assert_eq!(interp.env().get("税"), Some(Value::Number(7)));
+
+        interp.define_reactive("甲", "乙").unwrap_err();
+        interp.run("◈ 乙 ⇐ ∅ ⋄").unwrap();
+        interp.define_reactive("甲", "乙 ⊞ ⊕").unwrap();
+        assert_eq!(
+            interp.define_reactive("乙", "甲"),
+            Err(LingError::RuntimeError(
+                "Reactive values depend on each other in a cycle".to_string()
+            ))
+        );
+        interp.run("乙 ⇐ ∃ ⋄").unwrap();
+        assert_eq!(interp.env().get("甲"), Some(Value::Number(3)));
+    }
+
+    #[test]
+    fn test_value_formatter_renders_printed_booleans() {
+        let mut interp = Interpreter::new();
+        interp.set_value_formatter(|value| match value {
+            Value::Boolean(true) => "真".to_string(),
+            Value::Boolean(false) => "假".to_string(),
+            other => other.to_string(),
+        });
+        interp.run("⟲ ⊕ ◁ ⊗, ⊕ ▷ ⊗ ⋄ ⟲ ∀ ⋄").unwrap();
+        assert_eq!(interp.output(), "真 假\n3\n");
+
+        // Without a formatter values print through Display
+        let mut interp = Interpreter::new();
+        interp.run("⟲ ⊕ ◁ ⊗ ⋄").unwrap();
+        assert_eq!(interp.output(), "true\n");
+    }
+
+    #[test]
+    fn test_while_counts_down_to_zero() {
+        let program = "◈ n ⇐ ∀ ⋄ ⟳ n ▷ ∅ ⦃ ◈ 步长 ⇐ ⊕ ⋄ n ⇐ n ⊟ 步长 ⋄ ⦄";
+        let env = run_program(program);
+        assert_eq!(env.get("n"), Some(Value::Number(0)));
+        assert_eq!(env.get("步长"), None);
+
+        let config = LingConfig {
+            max_recursion_depth: 2,
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        assert_eq!(
+            interp.run(program),
+            Err(LingError::RuntimeError(
+                "Loop exceeded 2 iterations".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_do_while_runs_body_once() {
+        let mut interp = Interpreter::new();
+        interp.run("⥁ ⦃ ⟲ ⟦一次⟧ ⋄ ⦄ ⟳ ∅ ⋄").unwrap();
+        assert_eq!(interp.output(), "一次\n");
+
+        let config = LingConfig {
+            max_recursion_depth: 3,
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        assert_eq!(
+            interp.run("执行 ⦃ ⟲ ⊕ ⋄ ⦄ 循环 ⊕ ⋄"),
+            Err(LingError::RuntimeError(
+                "Loop exceeded 3 iterations".to_string()
+            ))
+        );
+        assert_eq!(interp.output(), "1\n1\n1\n");
+    }
+
+    #[test]
+    fn test_for_over_huge_range_is_lazy_and_capped() {
+        let mut interp = Interpreter::new();
+        assert_eq!(
+            interp.run("遍历 i ∈ ⊕ … 10000000000 ⦃ ◈ 末 ⇐ i ⋄ ⦄"),
+            Err(LingError::RuntimeError(
+                "Loop exceeded 1000 iterations".to_string()
+            ))
+        );
+
+        let env = run_program("◈ 和 ⇐ ∅ ⋄ 遍历 i ∈ ⊕ … ℈ ⦃ 和 ⇐ 和 ⊞ i ⋄ ⦄");
+        assert_eq!(env.get("和"), Some(Value::Number(45)));
+
+        assert_eq!(
+            try_run_program("◈ 和 ⇐ 求和(⊕ … 10000000000) ⋄").err(),
+            Some(LingError::RuntimeError(
+                "range too large: more than 1048576 elements".to_string()
+            ))
+        );
+        let huge = Value::Range {
+            start: 1,
+            end: 10_000_000_000,
+            step: 1,
+        };
+        assert_eq!(huge.element_count(), Some(10_000_000_000));
+        assert_eq!(huge.elements().unwrap().nth(2), Some(Value::Number(3)));
+    }
+
+    #[test]
+    fn test_labeled_break_leaves_outer_loop() {
+        let nested = |jump: &str| {
+            format!(
+                "@外 遍历 甲 ∈ ⊕ … ∀ ⦃ 遍历 乙 ∈ ⊕ … ∀ ⦃ ⟲ 甲, 乙 ⋄ {} ⦄ ⟲ ⟦后⟧ ⋄ ⦄",
+                jump
+            )
+        };
+
+        let mut interp = Interpreter::new();
+        interp.run(&nested("跳出 ⋄")).unwrap();
+        assert_eq!(interp.output(), "1 1\n后\n2 1\n后\n3 1\n后\n");
+
+        let mut interp = Interpreter::new();
+        interp.run(&nested("跳出 @外 ⋄")).unwrap();
+        assert_eq!(interp.output(), "1 1\n");
+
+        let mut interp = Interpreter::new();
+        interp.run(&nested("继续 @外 ⋄")).unwrap();
+        assert_eq!(interp.output(), "1 1\n2 1\n3 1\n");
+
+        let mut interp = Interpreter::new();
+        assert_eq!(
+            interp.run(&nested("⇥ @内 ⋄")),
+            Err(LingError::RuntimeError(
+                "Unknown loop label: 内".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compound_if_condition_picks_branch() {
+        let program = |x: &str| {
+            format!(
+                "◈ x ⇐ {} ⋄ ◬ x ▷ ⊕⊕ 并 x ◁ ⊕⊕⊕⊕⊕ ◭ ⦃ ⟲ ⟦内⟧ ⋄ ⦄ ◮ ⦃ ⟲ ⟦外⟧ ⋄ ⦄",
+                x
+            )
+        };
+        for (x, expected) in [("∀", "内\n"), ("℞", "外\n"), ("⊕", "外\n")] {
+            let mut interp = Interpreter::new();
+            interp.run(&program(x)).unwrap();
+            assert_eq!(interp.output(), expected, "x = {}", x);
+        }
+
+        // The right side is skipped once the left decides the result
+        let env = run_program("◈ 甲 ⇐ ∅ 并 未定义 ⋄ ◈ 乙 ⇐ ⊕ 或 未定义 ⋄ ◈ 丙 ⇐ ∅ 或 ⊕ ⊙ ⊕ ⋄");
+        assert_eq!(env.get("甲"), Some(Value::Boolean(false)));
+        assert_eq!(env.get("乙"), Some(Value::Boolean(true)));
+        assert_eq!(env.get("丙"), Some(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_xor_and_implication_truth_tables() {
+        for (a, b, xor, implies) in [
+            ("∅", "∅", false, true),
+            ("∅", "⊕", true, true),
+            ("⊕", "∅", true, false),
+            ("⊕", "⊕", false, true),
+        ] {
+            let env = run_program(&format!("◈ 甲 ⇐ {a} 异或 {b} ⋄ ◈ 乙 ⇐ {a} 蕴含 {b} ⋄"));
+            assert_eq!(env.get("甲"), Some(Value::Boolean(xor)), "{a} 异或 {b}");
+            assert_eq!(env.get("乙"), Some(Value::Boolean(implies)), "{a} 蕴含 {b}");
+        }
+
+        // A false premise decides the implication without evaluating the right,
+        // and chains group to the right
+        let env = run_program("◈ 甲 ⇐ ∅ 蕴含 未定义 ⋄ ◈ 乙 ⇐ ∅ 蕴含 ⊕ 蕴含 ∅ ⋄");
+        assert_eq!(env.get("甲"), Some(Value::Boolean(true)));
+        assert_eq!(env.get("乙"), Some(Value::Boolean(true)));
+        assert_eq!(utils::normalize_operator("异或"), "^");
+        assert_eq!(utils::normalize_operator("蕴含"), "->");
+    }
+
+    #[test]
+    fn test_symbolic_logical_operators_short_circuit() {
+        let env = run_program(
+            "◈ 甲 ⇐ ∅ ▷ ⊕ ∧ 未定义 ⋄ ◈ 乙 ⇐ ⊕ ▷ ∅ ∨ 未定义 ⋄ \
+             ◈ 丙 ⇐ ¬ ⊕ ▷ ∅ ⋄ ◈ 丁 ⇐ ¬ ¬ ⊕ ∧ ¬ ∅ ⋄",
+        );
+        assert_eq!(env.get("甲"), Some(Value::Boolean(false)));
+        assert_eq!(env.get("乙"), Some(Value::Boolean(true)));
+        assert_eq!(env.get("丙"), Some(Value::Boolean(false)));
+        assert_eq!(env.get("丁"), Some(Value::Boolean(true)));
+
+        assert_eq!(
+            try_run_program("◈ 甲 ⇐ ⊕ ▷ ∅ ∧ 未定义 ⋄").err(),
+            Some(LingError::UndefinedVariable("未定义".to_string()))
+        );
+        assert_eq!(utils::normalize_operator("∧"), "&&");
+        assert_eq!(utils::normalize_operator("∨"), "||");
+        assert!(utils::is_alien_operator("¬"));
+    }
+
+    #[test]
+    fn test_if_branch_assignments_survive_their_scope() {
+        let program =
+            |cond: &str| format!("◈ 甲 ⇐ ∅ ⋄ ◬ {cond} ◭ ⦃ ◈ 乙 ⇐ ⊕ ⋄ 甲 ⇐ 乙 ⋄ ⦄ ◮ ⦃ 甲 ⇐ ⊗ ⋄ ⦄");
+
+        let env = run_program(&program("⊕ ▷ ∅"));
+        assert_eq!(env.get("甲"), Some(Value::Number(1)));
+        assert_eq!(env.get("乙"), None);
+
+        let env = run_program(&program("∅ ▷ ⊕"));
+        assert_eq!(env.get("甲"), Some(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_declaration_without_initializer() {
+        let env = run_program("◈ x ⋄ ◬ ⊕ ▷ ∅ ◭ ⦃ x ⇐ ⊗ ⋄ ⦄ ◮ ⦃ x ⇐ ∀ ⋄ ⦄");
+        assert_eq!(env.get("x"), Some(Value::Number(2)));
+        let env = run_program("◈ x ⋄ ◈ y ⇐ x ⋄");
+        assert_eq!(env.get("y"), Some(Value::Void));
+
+        let config = LingConfig {
+            strict_mode: true,
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        assert_eq!(
+            interp.run("◈ x ⋄ ⟲ x ⋄"),
+            Err(LingError::RuntimeError(
+                "Variable 'x' used before assignment".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_return_several_values_and_destructure() {
+        let env = run_program(
+            "⟡ 商余 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊘ b, a ⊟ a ⊘ b ⊠ b ⋄ ⦄ ◈ 甲, 乙 ⇐ 商余(∄℟, ℥) ⋄ ◈ 对 ⇐ 商余(℈, ∀) ⋄",
+        );
+        assert_eq!(env.get("甲"), Some(Value::Number(3)));
+        assert_eq!(env.get("乙"), Some(Value::Number(2)));
+        assert_eq!(
+            env.get("对"),
+            Some(Value::Array(vec![Value::Number(3), Value::Number(0)]))
+        );
+
+        let result = try_run_program("◈ 甲, 乙, 丙 ⇐ ⟬⊕, ⊗⟭ ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::RuntimeError(
+                "Cannot destructure 2 values into 3 names".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_switch_runs_matching_arm_and_falls_through_on_request() {
+        let program = |fall: &str, x: &str| {
+            format!(
+                "◈ x ⇐ {x} ⋄ ⋔ {fall} x ⦃ \
+                 ⊢ ⊕, ⊗ ⦃ ⟲ ⟦小⟧ ⋄ ⦄ \
+                 ⊢ ∀ ⦃ ⟲ ⟦三⟧ ⋄ ⇥ ⋄ ⦄ \
+                 ⊣ ⦃ ⟲ ⟦其他⟧ ⋄ ⦄ ⦄"
+            )
+        };
+        for (fall, x, expected) in [
+            ("", "⊗", "小\n"),
+            ("", "∀", "三\n"),
+            ("", "℧", "其他\n"),
+            // 1 and 2 share the 3 arm's output, and its break stops the switch
+            ("⤓", "⊕", "小\n三\n"),
+            ("贯穿", "⊗", "小\n三\n"),
+            ("⤓", "℧", "其他\n"),
+        ] {
+            let mut interp = Interpreter::new();
+            interp.run(&program(fall, x)).unwrap();
+            assert_eq!(interp.output(), expected, "{fall} x = {x}");
+        }
+
+        // The switch swallows its own break but not an enclosing loop's continue
+        let env = run_program(
+            "◈ n ⇐ ∅ ⋄ ◈ 和 ⇐ ∅ ⋄ ⟳ n ◁ ℧ ⦃ n ⇐ n ⊞ ⊕ ⋄ \
+             ⋔ n ⦃ ⊢ ⊗ ⦃ ↻ ⋄ ⦄ ⊣ ⦃ ⇥ ⋄ ⦄ ⦄ 和 ⇐ 和 ⊞ n ⋄ ⦄",
+        );
+        assert_eq!(env.get("和"), Some(Value::Number(8)));
+    }
+
+    #[test]
+    fn test_try_catches_division_by_zero() {
+        let mut interp = Interpreter::new();
+        interp
+            .run("尝试 ⦃ ⟲ ⟦前⟧ ⋄ ◈ 甲 ⇐ ⊕ ⊘ ∅ ⋄ ⟲ ⟦后⟧ ⋄ ⦄ 捕获 错 ⦃ ⟲ 错 ⋄ ⦄")
+            .unwrap();
+        assert_eq!(interp.output(), "前\nDivision by zero\n");
+
+        // A return inside the try block still leaves the function
+        let mut interp = Interpreter::new();
+        interp
+            .run("⟡ 取 ⦃ ⦄ ⇒ ⦃ ⟃ ⦃ ⟴ ⊗ ⋄ ⦄ ⟄ (错) ⦃ ⟴ ∅ ⋄ ⦄ ⟴ ∀ ⋄ ⦄ ◈ 值 ⇐ 取() ⋄")
+            .unwrap();
+        assert_eq!(interp.env().get("值"), Some(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_try_does_not_catch_resource_limits() {
+        let config = LingConfig {
+            max_recursion_depth: 5,
+            max_output_bytes: Some(8),
+            ..LingConfig::default()
+        };
+        let cases = [
+            ("⟳ ⊕ ⦃ ⦄", "Loop exceeded 5 iterations"),
+            ("深() ⋄", "Maximum recursion depth 5 exceeded"),
+            ("⟲ ⟦一二三四⟧ ⋄", "output limit exceeded"),
+        ];
+        for (body, message) in cases {
+            let mut interp = Interpreter::with_config(config.clone());
+            let source = format!(
+                "⟡ 深 ⦃ ⦄ ⇒ ⦃ ⟴ 深() ⋄ ⦄ 尝试 ⦃ {} ⦄ 捕获 错 ⦃ ◈ 捕 ⇐ 错 ⋄ ⦄",
+                body
+            );
+            assert_eq!(
+                interp.run(&source),
+                Err(LingError::RuntimeError(message.to_string())),
+                "{}",
+                body
+            );
+            assert_eq!(interp.env().get("捕"), None);
+        }
+        assert!(LingError::StackOverflow(64).is_resource_limit());
+        assert!(!LingError::DivisionByZero.is_resource_limit());
+    }
+
+    #[test]
+    fn test_memoized_function_runs_body_once_per_argument() {
+        let square = "⟡ 平方 ⦃ n ⦄ ⇒ ⦃ ⟲ ⟦算⟧ ⋄ ⟴ n ⊠ n ⋄ ⦄";
+        let calls = "◈ a ⇐ 平方(∀) ⋄ ◈ b ⇐ 平方(∀) ⋄ ◈ c ⇐ 平方(⊗) ⋄";
+
+        let mut plain = Interpreter::new();
+        plain.run(&format!("{} {}", square, calls)).unwrap();
+        assert_eq!(plain.output(), "算\n算\n算\n");
+
+        let mut memoized = Interpreter::new();
+        memoized.run(&format!("⟐ {} {}", square, calls)).unwrap();
+        assert_eq!(memoized.output(), "算\n算\n");
+        assert_eq!(memoized.env().get("b"), Some(Value::Number(9)));
+        assert_eq!(memoized.env().get("c"), Some(Value::Number(4)));
+
+        // The cache survives between runs but not a reset
+        memoized.run("◈ d ⇐ 平方(∀) ⋄").unwrap();
+        assert_eq!(memoized.output(), "算\n算\n");
+        memoized.reset();
+        memoized.run(&format!("记忆 {} {}", square, calls)).unwrap();
+        assert_eq!(memoized.output(), "算\n算\n");
+
+        // Unknown attributes are ignored alongside @记忆
+        let mut attributed = Interpreter::new();
+        attributed
+            .run(&format!("@纯 @记忆 {} {}", square, calls))
+            .unwrap();
+        assert_eq!(attributed.output(), "算\n算\n");
+    }
+
+    #[test]
+    fn test_unwrap_value_accepts_wrapped_and_bare_expressions() {
+        let mut env = Environment::new();
+        let mut sink = Vec::new();
+        let mut ctx = executor::ExecContext::new(&mut sink, LingConfig::default());
+
+        let value = LingParser::parse(Rule::VALUE, "⊕⊕⊕")
+            .unwrap()
+            .next()
+            .unwrap();
+        let expr = executor::unwrap_value(value).unwrap();
+        assert_eq!(expr.as_rule(), Rule::EXPRESSION);
+        assert_eq!(
+            evaluator::evaluate_expression(expr, &mut env, &mut ctx),
+            Ok(Value::Number(3))
+        );
+
+        let number = LingParser::parse(Rule::NUMBER, "五")
+            .unwrap()
+            .next()
+            .unwrap();
+        let bare = executor::unwrap_value(number).unwrap();
+        assert_eq!(bare.as_rule(), Rule::NUMBER);
+        assert_eq!(
+            evaluator::evaluate_expression(bare, &mut env, &mut ctx),
+            Ok(Value::Number(5))
+        );
+    }
+
+    #[test]
+    fn test_print_multiple_values() {
+        let mut interp = Interpreter::new();
+        interp.load("◈ 名 ⇐ ⟦你好⟧ ⋄ ⟲ 42, 名 ⋄").unwrap();
+        while interp.step().unwrap().is_some() {}
+        assert_eq!(interp.output(), "42 你好\n");
+    }
+
+    #[test]
+    fn test_exit_skips_remaining_statements() {
+        let env = run_program("◈ 前 ⇐ ⊕ ⋄ ⏏ ⊗ ⋄ ◈ 后 ⇐ ⊗ ⋄");
+        assert_eq!(env.get("前"), Some(Value::Number(1)));
+        assert_eq!(env.get("后"), None);
+
+        let mut interp = Interpreter::new();
+        interp.load("◈ 前 ⇐ ⊕ ⋄ 退出 ⊕⊕⊕ ⋄ ⟲ ⟦不会输出⟧ ⋄").unwrap();
+        while interp.step().unwrap().is_some() {}
+        assert_eq!(interp.exit_code(), Some(3));
+        assert_eq!(interp.output(), "");
+
+        let mut interp = Interpreter::new();
+        assert_eq!(
+            interp.run("◈ 前 ⇐ ⊕ ⋄ 退出 ⊕⊕ ⋄ ◈ 后 ⇐ ⊗ ⋄"),
+            Ok(Value::Number(2))
+        );
+
+        let mut env = Environment::new();
+        let pair = LingParser::parse(Rule::PROGRAM, "退出 ⊕⊕⊕⊕ ⋄ ◈ 后 ⇐ ⊗ ⋄")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(
+            executor::execute_program(pair, &mut env),
+            Ok(Value::Number(4))
+        );
+        assert_eq!(env.get("后"), None);
+    }
+
+    #[test]
+    fn test_deep_nesting_reports_stack_overflow() {
+        let config = LingConfig {
+            max_parse_depth: 64,
+            ..LingConfig::default()
+        };
+        let source = format!("◈ x ⇐ {}⊕{} ⋄", "(".repeat(40), ")".repeat(40));
+        let mut interp = Interpreter::with_config(config);
+        interp.load(&source).unwrap();
+
+        assert_eq!(interp.step(), Err(LingError::StackOverflow(64)));
+    }
+
+    #[test]
+    fn test_deep_parentheses_fail_to_parse_gracefully() {
+        let source = format!("◈ x ⇐ {}⊕{} ⋄", "(".repeat(10_000), ")".repeat(10_000));
+        let mut interp = Interpreter::new();
+        assert_eq!(
+            interp.load(&source),
+            Err(LingError::ParseError(
+                "expression nesting too deep".to_string()
+            ))
+        );
+        assert!(
+            parser::parse_program(&source)
+                .unwrap_err()
+                .contains("nesting too deep")
+        );
+
+        // Brackets inside strings don't count towards the limit
+        let quoted = format!("◈ s ⇐ ⟦{}⟧ ⋄", "(".repeat(10_000));
+        assert!(interp.load(&quoted).is_ok());
+    }
+
+    #[test]
+    fn test_lookalike_glyphs_parse_after_canonicalization() {
+        // ⴲ is a Tifinagh letter that renders like the circled plus ⊕
+        let source = "◈ s ⇐ ⟦甲⬦⟧ ⴲ ⟦乙⟧ ⬦";
+        assert_eq!(parser::canonicalize_glyphs(source), "◈ s ⇐ ⟦甲⬦⟧ ⊕ ⟦乙⟧ ⋄");
+
+        let mut interp = Interpreter::new();
+        interp.run(source).unwrap();
+        assert_eq!(
+            interp.env().get("s"),
+            Some(Value::String("甲⬦乙".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_identifier_glyphs_are_not_canonicalized() {
+        // ☉ and ◇ look like ⊙ and ⋄ but are valid variable names
+        let source = "◈ ☉ ⇐ ⊕⊕⊕ ⋄ ◈ ◇ ⇐ ☉ ⊞ ⊕ ⋄";
+        assert_eq!(parser::canonicalize_glyphs(source), source);
+
+        let env = run_program(source);
+        assert_eq!(env.get("☉"), Some(Value::Number(3)));
+        assert_eq!(env.get("◇"), Some(Value::Number(4)));
+
+        let mut interp = Interpreter::new();
+        assert_eq!(interp.run("◈ ☉ ⇐ ⊕⊕⊕ ⋄ ☉ ⋄"), Ok(Value::Number(3)));
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Concatenation vs addition tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_concat_operator_always_concatenates() {
+        let env = run_program("◈ 串 ⇐ ⊕ ⧺ ⊗ ⋄ ◈ 长串 ⇐ ⊕⊕⊕ ⧺ ⟦个⟧ ⧺ ⊗⊗ ⋄");
+        assert_eq!(env.get("串"), Some(Value::String("12".to_string())));
+        assert_eq!(env.get("长串"), Some(Value::String("3个2".to_string())));
+    }
+
+    #[test]
+    fn test_add_operator_always_adds() {
+        let env = run_program("◈ 和 ⇐ ⊕ ⊞ ⊗ ⋄ ◈ 数和 ⇐ ⟦40⟧ ⊞ ⊗ ⋄");
+        assert_eq!(env.get("和"), Some(Value::Number(3)));
+        assert_eq!(env.get("数和"), Some(Value::Number(42)));
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Builtin function tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_builtin_char_vs_byte_length() {
+        let env = run_program(
+            "◈ 字数 ⇐ 字符长度(⟦你好⟧) ⋄ ◈ 字节 ⇐ 字节长度(⟦你好⟧) ⋄ ◈ 长 ⇐ 长度(⟦你好⟧) ⋄",
+        );
+        assert_eq!(env.get("字数"), Some(Value::Number(2)));
+        assert_eq!(env.get("字节"), Some(Value::Number(6)));
+        assert_eq!(env.get("长"), Some(Value::Number(2)));
+    }
+
+    #[test]
+    fn test_builtin_length_rejects_non_string() {
+        let result = try_run_program("◈ 字节 ⇐ 字节长度(⊕⊕⊕) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::TypeError {
+                expected: "string".to_string(),
+                found: "number".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_builtin_reverse_and_rotate_array() {
+        let env = run_program(
+            "◈ a ⇐ ⟬⊕, ⊗, ∀⟭ ⋄ ◈ 反 ⇐ 数组反转(a) ⋄ ◈ 左 ⇐ 旋转(a, ⊕) ⋄ ◈ 右 ⇐ 旋转(a, ∅ ⊟ ⊕) ⋄ ◈ 绕 ⇐ 旋转(a, ℥) ⋄",
+        );
+        let array = |items: [i64; 3]| Some(Value::Array(items.map(Value::Number).to_vec()));
+        assert_eq!(env.get("反"), array([3, 2, 1]));
+        assert_eq!(env.get("左"), array([2, 3, 1]));
+        assert_eq!(env.get("右"), array([3, 1, 2]));
+        assert_eq!(env.get("绕"), array([3, 1, 2]));
+        assert_eq!(env.get("a"), array([1, 2, 3]));
+
+        let result = try_run_program("◈ r ⇐ 旋转(⟦abc⟧, ⊕) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::TypeError {
+                expected: "array".to_string(),
+                found: "string".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_builtin_chunks_and_windows() {
+        let env = run_program(
+            "◈ a ⇐ ⟬⊕, ⊗, ∀, ℧, ℥⟭ ⋄ ◈ 块 ⇐ 分块(a, ⊗) ⋄ ◈ 窗 ⇐ 窗口(a, ∀) ⋄ ◈ 空 ⇐ 窗口(a, ℞) ⋄",
+        );
+        let arrays = |groups: &[&[i64]]| {
+            let groups = groups
+                .iter()
+                .map(|g| Value::Array(g.iter().map(|&n| Value::Number(n)).collect()))
+                .collect();
+            Some(Value::Array(groups))
+        };
+        assert_eq!(env.get("块"), arrays(&[&[1, 2], &[3, 4], &[5]]));
+        assert_eq!(env.get("窗"), arrays(&[&[1, 2, 3], &[2, 3, 4], &[3, 4, 5]]));
+        assert_eq!(env.get("空"), arrays(&[]));
+
+        let result = try_run_program("◈ r ⇐ 分块(⟬⊕⟭, ∅) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::InvalidOperation {
+                op: "分块".to_string(),
+                left: "array".to_string(),
+                right: "size 0".to_string(),
+            })
+        );
+        assert!(matches!(
+            try_run_program("◈ r ⇐ 窗口(⟦abc⟧, ⊕) ⋄"),
+            Err(LingError::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_builtin_sum_and_product() {
+        let env = run_program(
+            "◈ 和 ⇐ 求和(⟬⊕, ⊗, ∀⟭) ⋄ ◈ 积 ⇐ 求积(⟬⊕, ⊗, ∀⟭) ⋄ ◈ 空和 ⇐ 求和(⟬⟭) ⋄ ◈ 空积 ⇐ 求积(⟬⟭) ⋄",
+        );
+        assert_eq!(env.get("和"), Some(Value::Number(6)));
+        assert_eq!(env.get("积"), Some(Value::Number(6)));
+        assert_eq!(env.get("空和"), Some(Value::Number(0)));
+        assert_eq!(env.get("空积"), Some(Value::Number(1)));
+    }
+
+    #[test]
+    fn test_builtin_product_overflow() {
+        let result = try_run_program("◈ 积 ⇐ 求积(⟬4294967296, 4294967296⟭) ⋄");
+        assert!(matches!(result, Err(LingError::RuntimeError(_))));
+
+        let result = try_run_program("◈ 和 ⇐ 求和(⟬⊕, ⟦二⟧⟭) ⋄");
+        assert!(matches!(result, Err(LingError::TypeError { .. })));
+    }
+
+    #[test]
+    fn test_builtin_zip() {
+        let env = run_program("◈ 对 ⇐ 打包(⟬⊕, ⊗⟭, ⟬∀, ⊕, ⊗⟭) ⋄");
+        assert_eq!(
+            env.get("对"),
+            Some(Value::Array(vec![
+                Value::Array(vec![Value::Number(1), Value::Number(3)]),
+                Value::Array(vec![Value::Number(2), Value::Number(1)]),
+            ]))
+        );
+
+        let result = try_run_program("◈ 对 ⇐ 打包(⟬⊕⟭, ⊗) ⋄");
+        assert!(matches!(result, Err(LingError::TypeError { .. })));
+    }
+
+    #[test]
+    fn test_builtin_bit_count_and_parity() {
+        let env = run_program(
+            "◈ 位 ⇐ 位数(⊕⊕⊕⊕⊕⊕⊕) ⋄ ◈ 偶 ⇐ 是偶数(⊗) ⋄ ◈ 奇 ⇐ 是奇数(⊗) ⋄ ◈ 负 ⇐ 是奇数(∅ ⊟ ∀) ⋄",
+        );
+        assert_eq!(env.get("位"), Some(Value::Number(3)));
+        assert_eq!(env.get("偶"), Some(Value::Boolean(true)));
+        assert_eq!(env.get("奇"), Some(Value::Boolean(false)));
+        assert_eq!(env.get("负"), Some(Value::Boolean(true)));
+
+        let result = try_run_program("◈ 位 ⇐ 位数(⟦七⟧) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::TypeError {
+                expected: "number".to_string(),
+                found: "string".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_builtin_pack_and_unpack_bits_round_trip() {
+        let env = run_program(
+            "◈ 真 ⇐ ⊕ ▷ ∅ ⋄ ◈ 假 ⇐ ∅ ▷ ⊕ ⋄ ◈ a ⇐ ⟬真, 假, 真, 真⟭ ⋄ ◈ 包 ⇐ 打包位(a) ⋄ ◈ 解 ⇐ 解包位(包, ℧) ⋄",
+        );
+        assert_eq!(env.get("包"), Some(Value::Number(13)));
+        assert_eq!(env.get("解"), env.get("a"));
+
+        let result = try_run_program("◈ 包 ⇐ 打包位(⟬⊕⟭) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::TypeError {
+                expected: "boolean".to_string(),
+                found: "number".to_string(),
+            })
+        );
+        let result = try_run_program("◈ 包 ⇐ 打包位(⊕..℞℥) ⋄");
+        assert!(matches!(result, Err(LingError::InvalidOperation { .. })));
+    }
+
+    #[test]
+    fn test_builtin_clock() {
+        let env = run_program("◈ 现在 ⇐ 时间() ⋄");
+        assert!(matches!(env.get("现在"), Some(Value::Number(ms)) if ms > 0));
+
+        let result = try_run_program("◈ 现在 ⇐ 时间(⊕) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::ArgumentMismatch {
+                expected: 0,
+                found: 1
+            })
+        );
+
+        let config = LingConfig {
+            fixed_clock_ms: Some(1234),
+            ..LingConfig::default()
+        };
+        let mut interp = Interpreter::with_config(config);
+        assert_eq!(interp.run("时间()"), Ok(Value::Number(1234)));
+    }
+
+    #[test]
+    fn test_builtin_reverse_and_palindrome() {
+        let env = run_program(
+            "◈ 反 ⇐ 反转(⟦你好⟧) ⋄ ◈ 是 ⇐ 是回文(⟦上海自来水来自海上⟧) ⋄ ◈ 否 ⇐ 是回文(⟦你好⟧) ⋄",
+        );
+        assert_eq!(env.get("反"), Some(Value::String("好你".to_string())));
+        assert_eq!(env.get("是"), Some(Value::Boolean(true)));
+        assert_eq!(env.get("否"), Some(Value::Boolean(false)));
+
+        let result = try_run_program("◈ 反 ⇐ 反转(⊕) ⋄");
+        assert!(matches!(result, Err(LingError::TypeError { .. })));
+    }
+
+    #[test]
+    fn test_builtin_pad_to_display_width() {
+        let env = run_program(
+            "◈ 左 ⇐ 左填充(⟦你好⟧, ℞, ⟦·⟧) ⋄ ◈ 右 ⇐ 右填充(⟦你好⟧, ℞, ⟦·⟧) ⋄ ◈ 长 ⇐ 左填充(⟦你好世界⟧, ℞, ⟦·⟧) ⋄ ◈ 宽 ⇐ 右填充(⟦ab⟧, ℞, ⟦　⟧) ⋄",
+        );
+        assert_eq!(env.get("左"), Some(Value::String("··你好".to_string())));
+        assert_eq!(env.get("右"), Some(Value::String("你好··".to_string())));
+        assert_eq!(env.get("长"), Some(Value::String("你好世界".to_string())));
+        assert_eq!(env.get("宽"), Some(Value::String("ab　　".to_string())));
+
+        let result = try_run_program("◈ 大 ⇐ 左填充(⟦你⟧, ∄∅∅∅∅∅∅∅∅∅∅, ⟦·⟧) ⋄");
+        assert!(matches!(
+            result,
+            Err(LingError::RuntimeError(ref msg)) if msg.starts_with("pad width too large")
+        ));
+
+        let result = try_run_program("◈ 错 ⇐ 右填充(⟦你⟧, ∀, ⟦··⟧) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::InvalidOperation {
+                op: "右填充".to_string(),
+                left: "string".to_string(),
+                right: "pad of 2 chars".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_builtin_char_code_point_conversions() {
+        let env = run_program("◈ 字 ⇐ 字符(97) ⋄ ◈ 码 ⇐ 码点(字符(20320)) ⋄ ◈ 文码 ⇐ 码点(⟦你⟧) ⋄");
+        assert_eq!(env.get("字"), Some(Value::Char('a')));
+        assert_eq!(env.get("码"), Some(Value::Number(20320)));
+        assert_eq!(env.get("文码"), Some(Value::Number(20320)));
+
+        let result = try_run_program("◈ 码 ⇐ 码点(⟦你好⟧) ⋄");
+        assert!(matches!(result, Err(LingError::TypeError { .. })));
+
+        let code_point = builtins::lookup("码点").unwrap();
+        assert_eq!(code_point(&[Value::Char('你')]), Ok(Value::Number(20320)));
+
+        let result = try_run_program("◈ 字 ⇐ 字符(55296) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::RuntimeError(
+                "Invalid code point: 55296".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_builtin_dedup_keeps_first_occurrence() {
+        let env = run_program("◈ 数 ⇐ 去重(⟬⊕, ⊗, ⊕, ∀⟭) ⋄");
+        assert_eq!(
+            env.get("数"),
+            Some(Value::Array(vec![
+                Value::Number(1),
+                Value::Number(2),
+                Value::Number(3),
+            ]))
+        );
+
+        let result = try_run_program("◈ 数 ⇐ 去重(⊕) ⋄");
+        assert!(matches!(result, Err(LingError::TypeError { .. })));
+    }
+
+    #[test]
+    fn test_builtin_count_occurrences() {
+        let env = run_program("◈ 次 ⇐ 计数(⟬⊕, ⊗, ⊕, ∀⟭, ⊕) ⋄ ◈ 无 ⇐ 计数(⟬⊗⟭, ⊕) ⋄");
+        assert_eq!(env.get("次"), Some(Value::Number(2)));
+        assert_eq!(env.get("无"), Some(Value::Number(0)));
+    }
+
+    #[test]
+    fn test_builtin_map_from_pairs() {
+        let env = run_program(
+            "◈ 映 ⇐ 建映射(⟬⟬⟦甲⟧, ⊕⟭, ⟬⟦乙⟧, ⊗⟭⟭) ⋄ ◈ 值 ⇐ 映[⟦乙⟧] ⋄ ◈ 空 ⇐ 建映射(⟬⟭) ⋄",
+        );
+        assert_eq!(env.get("值"), Some(Value::Number(2)));
+        assert_eq!(env.get("空"), Some(Value::Map(Default::default())));
+
+        for bad in ["⟬⟬⟦甲⟧⟭⟭", "⟬⟬⊕, ⊗⟭⟭", "⟬⊕⟭"] {
+            let result = try_run_program(&format!("◈ 映 ⇐ 建映射({}) ⋄", bad));
+            assert!(
+                matches!(result, Err(LingError::TypeError { .. })),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_builtin_binary_search_and_sorted_insert() {
+        let env = run_program(
+            "◈ 数 ⇐ ⟬⊕, ∀, ℧, ℟⟭ ⋄ ◈ 在 ⇐ 二分查找(数, ℧) ⋄ ◈ 无 ⇐ 二分查找(数, ⊗) ⋄ \
+             ◈ 新 ⇐ 有序插入(数, ⊗) ⋄",
+        );
+        assert_eq!(env.get("在"), Some(Value::Number(2)));
+        assert_eq!(env.get("无"), Some(Value::Number(-1)));
+        assert_eq!(
+            env.get("新"),
+            Some(Value::Array(
+                [1, 2, 3, 4, 7].into_iter().map(Value::Number).collect()
+            ))
+        );
+
+        let result = try_run_program("◈ 位 ⇐ 二分查找(⟬⊕, ⊗⟭, ⟦甲⟧) ⋄");
+        assert!(matches!(result, Err(LingError::InvalidOperation { .. })));
+    }
+
+    #[test]
+    fn test_builtin_find_uses_char_index() {
+        let env = run_program("◈ 位 ⇐ 查找(⟦你好世界⟧, ⟦世界⟧) ⋄ ◈ 无 ⇐ 查找(⟦你好⟧, ⟦再见⟧) ⋄");
+        assert_eq!(env.get("位"), Some(Value::Number(2)));
+        assert_eq!(env.get("无"), Some(Value::Number(-1)));
+
+        let result = try_run_program("◈ 位 ⇐ 查找(⟦你好⟧, ⟦⟧) ⋄");
+        assert!(matches!(result, Err(LingError::InvalidOperation { .. })));
+    }
+
+    #[test]
+    fn test_builtin_count_substring_occurrences() {
+        let env = run_program(
+            "◈ 叠 ⇐ 出现次数(⟦哈哈哈哈哈⟧, ⟦哈哈⟧) ⋄ ◈ 词 ⇐ 出现次数(⟦你好世界，世界你好⟧, ⟦世界⟧) ⋄ ◈ 无 ⇐ 出现次数(⟦你好⟧, ⟦再见⟧) ⋄",
+        );
+        assert_eq!(env.get("叠"), Some(Value::Number(2)));
+        assert_eq!(env.get("词"), Some(Value::Number(2)));
+        assert_eq!(env.get("无"), Some(Value::Number(0)));
+
+        let result = try_run_program("◈ 次 ⇐ 出现次数(⟦你好⟧, ⟦⟧) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::InvalidOperation {
+                op: "出现次数".to_string(),
+                left: "string".to_string(),
+                right: "empty string".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_builtin_replace_all_occurrences() {
+        let env = run_program("◈ 新 ⇐ 替换(⟦一二一二一⟧, ⟦一⟧, ⟦三⟧) ⋄");
+        assert_eq!(env.get("新"), Some(Value::String("三二三二三".to_string())));
+    }
+
+    // ─── Helper: build a map value from string keys ───
+    fn map_of(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_builtin_merge_overrides_left() {
+        let merge = builtins::lookup("合并").unwrap();
+        let a = map_of(&[("甲", Value::Number(1)), ("乙", Value::Number(2))]);
+        let b = map_of(&[("乙", Value::Number(20)), ("丙", Value::Number(3))]);
+
+        let merged = merge(&[a.clone(), b]).unwrap();
+        assert_eq!(
+            merged,
+            map_of(&[
+                ("甲", Value::Number(1)),
+                ("乙", Value::Number(20)),
+                ("丙", Value::Number(3)),
+            ])
+        );
+        assert!(matches!(
+            merge(&[a, Value::Number(1)]),
+            Err(LingError::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_builtin_remove_absent_key_is_noop() {
+        let remove_key = builtins::lookup("删除键").unwrap();
+        let m = map_of(&[("甲", Value::Number(1))]);
+
+        assert_eq!(remove_key(&[m.clone(), "乙".into()]), Ok(m.clone()));
+        assert_eq!(remove_key(&[m, "甲".into()]), Ok(map_of(&[])));
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Lambda tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_lambda_passed_to_map() {
+        let env = run_program("◈ 倍 ⇐ 映射(⟬⊕, ⊗, ∀⟭, ⦅ n ⦆ ⇒ n ⊠ ⊗) ⋄");
+        assert_eq!(
+            env.get("倍"),
+            Some(Value::Array(vec![
+                Value::Number(2),
+                Value::Number(4),
+                Value::Number(6),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_fold_sums_with_adder_function() {
+        let env = run_program(
+            "⟡ 加 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊞ b ⋄ ⦄ ◈ 和 ⇐ 折叠(⟬⊕, ⊗, ∀, ℧⟭, ∅, 加) ⋄ \
+             ◈ 空 ⇐ 折叠(⟬⟭, ℥, 加) ⋄ ◈ 串 ⇐ 折叠(⟬⟦b⟧, ⟦c⟧⟭, ⟦a⟧, ⦅ s, x ⦆ ⇒ s ⧺ x) ⋄",
+        );
+        assert_eq!(env.get("和"), Some(Value::Number(10)));
+        assert_eq!(env.get("空"), Some(Value::Number(5)));
+        assert_eq!(env.get("串"), Some(Value::String("abc".to_string())));
+
+        let result = try_run_program("◈ 和 ⇐ 折叠(⟬⊕⟭, ∅, ⦅ n ⦆ ⇒ n) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::ArgumentMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert!(matches!(
+            try_run_program("◈ 和 ⇐ 折叠(⊕, ∅, ⦅ a, b ⦆ ⇒ a) ⋄"),
+            Err(LingError::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_partial_application_makes_increment_from_adder() {
+        let env = run_program(
+            "⟡ 加 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊞ b ⋄ ⦄ ◈ 增 ⇐ 部分应用(加, ⟬⊕⟭) ⋄ \
+             ◈ 甲 ⇐ 增(℞) ⋄ ◈ 乙 ⇐ 映射(⟬∅, ⊕⟭, 增) ⋄ ◈ 戊 ⇐ 部分应用(增, ⟬⊗⟭) ⋄ ◈ 丙 ⇐ 戊() ⋄",
+        );
+        assert_eq!(env.get("甲"), Some(Value::Number(7)));
+        assert_eq!(
+            env.get("乙"),
+            Some(Value::Array(vec![Value::Number(1), Value::Number(2)]))
+        );
+        assert_eq!(env.get("丙"), Some(Value::Number(3)));
+
+        let result =
+            try_run_program("⟡ 加 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊞ b ⋄ ⦄ ◈ f ⇐ 部分应用(加, ⟬⊕, ⊕, ⊕⟭) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::ArgumentMismatch {
+                expected: 2,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_defined_function_binds_params_in_own_scope() {
+        let env =
+            run_program("◈ a ⇐ ℈ ⋄ ⟡ 差 ⦃ a, b ⦄ ⇒ ⦃ ◈ 局 ⇐ a ⊟ b ⋄ ⟴ 局 ⋄ ⦄ ◈ 甲 ⇐ 差 ⦅ ℥, ⊗ ⦆ ⋄");
+        assert_eq!(env.get("甲"), Some(Value::Number(3)));
+        assert_eq!(env.get("a"), Some(Value::Number(9)));
+        assert_eq!(env.get("局"), None);
+        assert!(matches!(env.get("差"), Some(Value::Function(_))));
+
+        assert_eq!(
+            try_run_program("⟡ 差 ⦃ a, b ⦄ ⇒ ⦃ ⟴ a ⊟ b ⋄ ⦄ ◈ 甲 ⇐ 差 ⦅ ℥ ⦆ ⋄").err(),
+            Some(LingError::ArgumentMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_lambda_called_through_variable() {
+        let env = run_program("◈ 加 ⇐ ⦅ a, b ⦆ ⇒ ⦃ ⟴ a ⊞ b ⋄ ⦄ ⋄ ◈ 和 ⇐ 加(⊗⊗, ∀) ⋄");
+        assert_eq!(env.get("和"), Some(Value::Number(5)));
+
+        let result = try_run_program("◈ 加 ⇐ ⦅ a, b ⦆ ⇒ a ⊞ b ⋄ ◈ 和 ⇐ 加(⊕) ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::ArgumentMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_equal_argument_tuples_hash_equally() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash_of(args: &[Value]) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            args.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let args = |n| {
+            vec![
+                Value::Number(n),
+                Value::String("你好".to_string()),
+                Value::Array(vec![Value::Boolean(true)]),
+                Value::Map([("键".to_string(), Value::Number(7))].into_iter().collect()),
+            ]
+        };
+        assert_eq!(hash_of(&args(1)), hash_of(&args(1)));
+        assert_ne!(hash_of(&args(1)), hash_of(&args(2)));
+        assert!(args(1).iter().all(|v| v.ensure_hashable().is_ok()));
+
+        let env = run_program("◈ f ⇐ ⦅ x ⦆ ⇒ x ⋄");
+        let nested = Value::Array(vec![env.get("f").unwrap()]);
+        assert!(matches!(
+            nested.ensure_hashable(),
+            Err(LingError::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_identical_functions_are_not_equal() {
+        let env = run_program("◈ f ⇐ ⦅ x ⦆ ⇒ x ⋄ ◈ g ⇐ ⦅ x ⦆ ⇒ x ⋄");
+        let f = env.get("f").unwrap();
+        let g = env.get("g").unwrap();
+        assert_ne!(f, g);
+        assert_eq!(f, f.clone());
+        assert!(!utils::values_equal(&f, &g));
+    }
+
+    #[test]
+    fn test_closures_capture_only_free_variables() {
+        let env = run_program(
+            "⟡ 造 ⦃ n ⦄ ⇒ ⦃ ◈ 无关 ⇐ ℈ ⋄ ◈ 步 ⇐ n ⊞ ⊕ ⋄ \
+             ◈ 加 ⇐ ⦅ x ⦆ ⇒ x ⊞ 步 ⋄ ⟡ 倍 ⦃ y ⦄ ⇒ ⦃ ⟴ y ⊠ n ⋄ ⦄ ⟴ ⟬加, 倍⟭ ⋄ ⦄ \
+             ◈ 组 ⇐ 造 ⦅ ⊗ ⦆ ⋄ ◈ 加 ⇐ 组[∅] ⋄ ◈ 倍 ⇐ 组[⊕] ⋄ ◈ 甲 ⇐ 加(∀) ⋄ ◈ 乙 ⇐ 倍(∀) ⋄",
+        );
+        assert_eq!(env.get("甲"), Some(Value::Number(6)));
+        assert_eq!(env.get("乙"), Some(Value::Number(6)));
+
+        let Some(Value::Array(closures)) = env.get("组") else {
+            panic!("组 is not an array");
+        };
+        let captured: Vec<Vec<&str>> = closures
+            .iter()
+            .map(|closure| match closure {
+                Value::Function(func) => func
+                    .captured
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect(),
+                other => panic!("{:?} is not a function", other),
+            })
+            .collect();
+        assert_eq!(captured, vec![vec!["步"], vec!["n"]]);
+    }
+
+    #[test]
+    fn test_function_body_is_parsed_at_definition() {
+        let env = run_program(
+            "⟡ 差 ⦃ a, b ⦄ ⇒ ⦃ ◈ 局 ⇐ a ⊟ b ⋄ ⟴ 局 ⋄ ⦄ ◈ f ⇐ ⦅ x ⦆ ⇒ x ⋄ ◈ g ⇐ ⦅ x ⦆ ⇒ x ⋄",
+        );
+        let Some(Value::Function(diff)) = env.get("差") else {
+            panic!("差 is not a function");
+        };
+        assert_eq!(diff.body.source(), "◈ 局 ⇐ a ⊟ b ⋄\n⟴ 局 ⋄");
+        let program = diff.body.statements().next().unwrap();
+        let statements = program
+            .into_inner()
+            .filter(|p| p.as_rule() == Rule::STATEMENT);
+        assert_eq!(statements.count(), 2);
+
+        // Identical bodies share one parse
+        let (Some(Value::Function(f)), Some(Value::Function(g))) = (env.get("f"), env.get("g"))
+        else {
+            panic!("f and g are not functions");
+        };
+        assert_eq!(f.body.source().as_ptr(), g.body.source().as_ptr());
+
+        assert!(matches!(
+            value::FunctionBody::parse("⟴ ⦃"),
+            Err(LingError::ParseError(_))
+        ));
+    }
+
+    // ═══════════════════════════════════════════════════════════════
+    //  Range tests
+    // ═══════════════════════════════════════════════════════════════
+
+    #[test]
+    fn test_stepped_range_sums_odd_numbers() {
+        let env = run_program("◈ 和 ⇐ 求和(⊕ … ⊕⊕⊕⊕⊕⊕⊕⊕⊕ 步 ⊗) ⋄");
+        assert_eq!(env.get("和"), Some(Value::Number(25)));
+
+        let mut interp = Interpreter::new();
+        interp.load("遍历 n ∈ ⊕ … ℈ 步 ⊗ ⦃ ⟲ n ⋄ ⦄").unwrap();
+        while interp.step().unwrap().is_some() {}
+        assert_eq!(interp.output(), "1\n3\n5\n7\n9\n");
+    }
+
+    #[test]
+    fn test_range_rejects_non_positive_step() {
+        let result = try_run_program("◈ r ⇐ ⊕ … ℈ 步 ∅ ⋄");
+        assert_eq!(
+            result.err(),
+            Some(LingError::InvalidOperation {
+                op: "步".to_string(),
+                left: "range".to_string(),
+                right: "step 0".to_string(),
+            })
+        );
+    }
 }